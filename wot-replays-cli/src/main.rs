@@ -69,15 +69,19 @@ fn print_raw_metadata(
     results_only: bool,
 ) -> Result<(), Box<dyn Error>> {
     if !battle_info_only && !results_only {
-        for i in 0..replay.data_chunks {
-            println!("{}", std::str::from_utf8(&replay.data[i as usize].payload)?);
+        for chunk in &replay.data {
+            println!("{}", std::str::from_utf8(&chunk.payload)?);
         }
         Ok(())
     } else if battle_info_only {
-        println!("{}", std::str::from_utf8(&replay.data[0].payload)?);
-        Ok(())
-    } else if replay.data_chunks >= 2 {
-        println!("{}", std::str::from_utf8(&replay.data[1].payload)?);
+        if let Some(chunk) = replay.data.first() {
+            println!("{}", std::str::from_utf8(&chunk.payload)?);
+            Ok(())
+        } else {
+            Err("Replay doesn't conatain battle info".into())
+        }
+    } else if let Some(chunk) = replay.data.get(1) {
+        println!("{}", std::str::from_utf8(&chunk.payload)?);
         Ok(())
     } else {
         Err("Replay doesn't conatain battle results".into())
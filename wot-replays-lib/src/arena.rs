@@ -0,0 +1,236 @@
+use crate::models::BattleInfo;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+pub mod catalog;
+
+pub use catalog::{MapBounds, MapCatalog, MapEntry, MapPoint};
+
+/// Contains the gameplay types (battle modes) of the arenas
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GameplayType {
+    /// Standard battle
+    Ctf,
+    /// Encounter battle
+    Domination,
+    Assault,
+    Nations,
+    Ctf2,
+    Domination2,
+    Assault2,
+    Fallout,
+    Fallout2,
+    Fallout3,
+    Fallout4,
+    Ctf30x30,
+    Domination30x30,
+    Sandbox,
+    Fallout5,
+    Fallout6,
+    Bootcamp,
+    /// Frontline
+    Epic,
+    /// Steel Hunter
+    BattleRoyale,
+    WeekendBrawl,
+    MapsTraining,
+    Unknown(u16),
+}
+
+const GAMEPLAY_NAMES: [(GameplayType, &str); 21] = [
+    (GameplayType::Ctf, "ctf"),
+    (GameplayType::Domination, "domination"),
+    (GameplayType::Assault, "assault"),
+    (GameplayType::Nations, "nations"),
+    (GameplayType::Ctf2, "ctf2"),
+    (GameplayType::Domination2, "domination2"),
+    (GameplayType::Assault2, "assault2"),
+    (GameplayType::Fallout, "fallout"),
+    (GameplayType::Fallout2, "fallout2"),
+    (GameplayType::Fallout3, "fallout3"),
+    (GameplayType::Fallout4, "fallout4"),
+    (GameplayType::Ctf30x30, "ctf30x30"),
+    (GameplayType::Domination30x30, "domination30x30"),
+    (GameplayType::Sandbox, "sandbox"),
+    (GameplayType::Fallout5, "fallout5"),
+    (GameplayType::Fallout6, "fallout6"),
+    (GameplayType::Bootcamp, "bootcamp"),
+    (GameplayType::Epic, "epic"),
+    (GameplayType::BattleRoyale, "battle_royale"),
+    (GameplayType::WeekendBrawl, "weekend_brawl"),
+    (GameplayType::MapsTraining, "maps_training"),
+];
+
+impl GameplayType {
+    /// Returns the gameplay name as `BattleInfo.gameplay_id` contains it, e.g. `ctf`
+    pub fn name(&self) -> Option<&'static str> {
+        GAMEPLAY_NAMES
+            .iter()
+            .find(|(g, _)| g == self)
+            .map(|(_, name)| *name)
+    }
+}
+
+impl From<u16> for GameplayType {
+    fn from(value: u16) -> Self {
+        GAMEPLAY_NAMES
+            .get(value as usize)
+            .map(|(g, _)| *g)
+            .unwrap_or(GameplayType::Unknown(value))
+    }
+}
+
+impl From<GameplayType> for u16 {
+    fn from(value: GameplayType) -> Self {
+        match value {
+            GameplayType::Unknown(v) => v,
+            g => GAMEPLAY_NAMES.iter().position(|(v, _)| *v == g).unwrap() as u16,
+        }
+    }
+}
+
+impl FromStr for GameplayType {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        GAMEPLAY_NAMES
+            .iter()
+            .find(|(_, name)| *name == s)
+            .map(|(g, _)| *g)
+            .ok_or("Unknown gameplay type")
+    }
+}
+
+/// Decoded `CommonBattleInfo.arena_type_id`. The lower 16 bits are the map id and the
+/// upper 16 bits are the gameplay type.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ArenaType {
+    /// Map id, the numeric prefix of the map name (`1` for `01_karelia`)
+    pub map_id: u16,
+    pub gameplay: GameplayType,
+}
+
+impl ArenaType {
+    pub fn from_packed(packed: u32) -> ArenaType {
+        ArenaType {
+            map_id: (packed & 0xFFFF) as u16,
+            gameplay: GameplayType::from((packed >> 16) as u16),
+        }
+    }
+
+    pub fn packed(&self) -> u32 {
+        ((u16::from(self.gameplay) as u32) << 16) | self.map_id as u32
+    }
+
+    /// Checks the arena type matches `BattleInfo.gameplay_id` and `BattleInfo.map_name`
+    pub fn check(&self, battle_info: &BattleInfo) -> Result<(), ArenaTypeMismatch> {
+        if self.gameplay.name() != Some(battle_info.gameplay_id.as_str()) {
+            return Err(ArenaTypeMismatch::Gameplay {
+                arena_type: self.gameplay,
+                gameplay_id: battle_info.gameplay_id.clone(),
+            });
+        }
+        let map_id = battle_info
+            .map_name
+            .split('_')
+            .next()
+            .and_then(|v| v.parse::<u16>().ok());
+        if map_id != Some(self.map_id) {
+            return Err(ArenaTypeMismatch::Map {
+                arena_type: self.map_id,
+                map_name: battle_info.map_name.clone(),
+            });
+        }
+        Ok(())
+    }
+}
+
+impl From<u32> for ArenaType {
+    fn from(packed: u32) -> Self {
+        ArenaType::from_packed(packed)
+    }
+}
+
+/// Mismatch of the decoded arena type and the battle info
+#[derive(Debug, Clone, PartialEq)]
+pub enum ArenaTypeMismatch {
+    Gameplay {
+        arena_type: GameplayType,
+        gameplay_id: String,
+    },
+    Map {
+        arena_type: u16,
+        map_name: String,
+    },
+}
+
+impl fmt::Display for ArenaTypeMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ArenaTypeMismatch::Gameplay {
+                arena_type,
+                gameplay_id,
+            } => write!(
+                f,
+                "Arena gameplay type {:?} doesn't match gameplay id {}",
+                arena_type, gameplay_id
+            ),
+            ArenaTypeMismatch::Map {
+                arena_type,
+                map_name,
+            } => write!(
+                f,
+                "Arena map id {} doesn't match map name {}",
+                arena_type, map_name
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ArenaTypeMismatch {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_arena_type() {
+        let arena_type = ArenaType::from_packed(0x0002_0005);
+        assert_eq!(
+            arena_type,
+            ArenaType {
+                map_id: 5,
+                gameplay: GameplayType::Assault
+            }
+        );
+        assert_eq!(arena_type.packed(), 0x0002_0005);
+        assert_eq!(
+            ArenaType::from_packed(0x0040_0001).gameplay,
+            GameplayType::Unknown(0x40)
+        );
+        assert_eq!(ArenaType::from_packed(0x0040_0001).packed(), 0x0040_0001);
+        assert_eq!("epic".parse(), Ok(GameplayType::Epic));
+    }
+
+    #[test]
+    fn test_arena_type_check() {
+        let json = fs::read_to_string("test_data/batlle_info.json").unwrap();
+        let mut battle_info: BattleInfo = serde_json::from_str(&json).unwrap();
+        let arena_type = ArenaType::from_packed(1);
+        assert_eq!(arena_type.check(&battle_info), Ok(()));
+
+        battle_info.gameplay_id = "domination".to_string();
+        assert!(matches!(
+            arena_type.check(&battle_info),
+            Err(ArenaTypeMismatch::Gameplay { .. })
+        ));
+        battle_info.gameplay_id = "ctf".to_string();
+        battle_info.map_name = "05_prohorovka".to_string();
+        assert!(matches!(
+            arena_type.check(&battle_info),
+            Err(ArenaTypeMismatch::Map { .. })
+        ));
+    }
+}
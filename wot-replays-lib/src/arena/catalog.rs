@@ -0,0 +1,247 @@
+use super::GameplayType;
+use crate::data::{extension, DataError, Keyed, Table};
+use crate::models::BattleInfo;
+use crate::packets::Vector3;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io;
+use std::path::Path;
+
+/// The bundled map snapshot. It has the bounds and the names of a few maps and no spawn or
+/// base positions: they come from the arena definitions of the game client, which are not
+/// redistributed with the crate. Load the catalog exported from the client with
+/// `MapCatalog::load` when the positions are required.
+const BUNDLED_SNAPSHOT: &str = include_str!("../../data/maps.json");
+
+/// A point on the map plane. `z` is the world space `z` axis (north).
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+pub struct MapPoint {
+    pub x: f32,
+    pub z: f32,
+}
+
+impl From<Vector3> for MapPoint {
+    fn from(v: Vector3) -> Self {
+        MapPoint { x: v.x, z: v.z }
+    }
+}
+
+/// World space bounding box of the playable map area
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct MapBounds {
+    pub bottom_left: MapPoint,
+    pub upper_right: MapPoint,
+}
+
+impl MapBounds {
+    pub fn width(&self) -> f32 {
+        self.upper_right.x - self.bottom_left.x
+    }
+
+    pub fn height(&self) -> f32 {
+        self.upper_right.z - self.bottom_left.z
+    }
+
+    pub fn contains(&self, point: MapPoint) -> bool {
+        point.x >= self.bottom_left.x
+            && point.x <= self.upper_right.x
+            && point.z >= self.bottom_left.z
+            && point.z <= self.upper_right.z
+    }
+
+    /// Converts the point into the `0.0..=1.0` minimap coordinates. The origin is the
+    /// bottom left corner of the map.
+    pub fn normalize(&self, point: MapPoint) -> MapPoint {
+        MapPoint {
+            x: (point.x - self.bottom_left.x) / self.width(),
+            z: (point.z - self.bottom_left.z) / self.height(),
+        }
+    }
+}
+
+/// Spawn and base positions of the gameplay type. The outer vectors are indexed by team,
+/// the first element is the team 1.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct GameplayPositions {
+    #[serde(default)]
+    pub team_spawns: Vec<Vec<MapPoint>>,
+    #[serde(default)]
+    pub team_bases: Vec<Vec<MapPoint>>,
+    /// Control points of the encounter battles
+    #[serde(default)]
+    pub control_points: Vec<MapPoint>,
+}
+
+/// Catalog entry of the map
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct MapEntry {
+    /// Map id, matches `ArenaType.map_id`
+    pub id: u16,
+    /// Map name as `BattleInfo.map_name` contains it, e.g. `01_karelia`
+    pub name: String,
+    /// Localized name in the language of the data source, e.g. `Karelia`
+    pub display_name: String,
+    pub bounds: MapBounds,
+    /// Positions keyed by the gameplay name, e.g. `ctf`
+    #[serde(default)]
+    pub gameplays: HashMap<String, GameplayPositions>,
+}
+
+impl MapEntry {
+    /// Returns the size of the map in meters: width and height
+    pub fn size(&self) -> (f32, f32) {
+        (self.bounds.width(), self.bounds.height())
+    }
+
+    pub fn positions(&self, gameplay: GameplayType) -> Option<&GameplayPositions> {
+        gameplay.name().and_then(|name| self.gameplays.get(name))
+    }
+}
+
+/// Map catalog keyed by the map name and the map id
+#[derive(Debug, Clone, Default)]
+pub struct MapCatalog {
+    table: Table<MapEntry>,
+}
+
+impl Keyed for MapEntry {
+    type Key = String;
+    type AltKey = u16;
+
+    fn key(&self) -> &String {
+        &self.name
+    }
+
+    fn alt_key(&self) -> &u16 {
+        &self.id
+    }
+}
+
+impl MapCatalog {
+    /// Creates the catalog of the entries. The later entries override the earlier ones.
+    pub fn from_entries(entries: impl IntoIterator<Item = MapEntry>) -> MapCatalog {
+        MapCatalog {
+            table: Table::from_entries(entries),
+        }
+    }
+
+    /// Loads the bundled map snapshot. It has no spawn or base positions, see
+    /// `MapCatalog::load`.
+    pub fn bundled() -> MapCatalog {
+        MapCatalog {
+            table: Table::bundled(BUNDLED_SNAPSHOT),
+        }
+    }
+
+    /// Loads the catalog from the JSON array of the `MapEntry` objects
+    pub fn from_json(reader: impl io::Read) -> Result<MapCatalog, DataError> {
+        Ok(MapCatalog {
+            table: Table::from_json(reader)?,
+        })
+    }
+
+    /// Loads the catalog from the `.json` file
+    pub fn load(path: impl AsRef<Path>) -> Result<MapCatalog, DataError> {
+        let path = path.as_ref();
+        match extension(path).as_deref() {
+            Some("json") => Self::from_json(io::BufReader::new(File::open(path)?)),
+            _ => Err(DataError::UnknownFormat(path.display().to_string())),
+        }
+    }
+
+    /// Adds the entry replacing the entries with the same name or id
+    pub fn insert(&mut self, entry: MapEntry) {
+        self.table.insert(entry);
+    }
+
+    /// Extends the catalog with the entries of another one
+    pub fn merge(&mut self, other: MapCatalog) {
+        self.table.merge(other.table);
+    }
+
+    pub fn by_name(&self, name: &str) -> Option<&MapEntry> {
+        self.table.get(&name.to_string())
+    }
+
+    pub fn by_id(&self, id: u16) -> Option<&MapEntry> {
+        self.table.get_by_alt_key(&id)
+    }
+
+    /// Returns the map of the battle by `BattleInfo.map_name`
+    pub fn for_battle(&self, battle_info: &BattleInfo) -> Option<&MapEntry> {
+        self.by_name(&battle_info.map_name)
+    }
+
+    pub fn entries(&self) -> &[MapEntry] {
+        self.table.entries()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries().is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_bundled_catalog() {
+        let catalog = MapCatalog::bundled();
+        let json = fs::read_to_string("test_data/batlle_info.json").unwrap();
+        let battle_info: BattleInfo = serde_json::from_str(&json).unwrap();
+        let entry = catalog.for_battle(&battle_info).unwrap();
+        assert_eq!(entry.display_name, "Karelia");
+        assert_eq!(entry.size(), (1000.0, 1000.0));
+        assert_eq!(catalog.by_id(1), Some(entry));
+        assert_eq!(
+            entry.bounds.normalize(MapPoint { x: 0.0, z: -250.0 }),
+            MapPoint { x: 0.5, z: 0.25 }
+        );
+        assert!(!entry.bounds.contains(MapPoint { x: 0.0, z: 501.0 }));
+        for entry in catalog.entries() {
+            assert!(entry.name.starts_with(&format!("{:02}_", entry.id)));
+        }
+    }
+
+    #[test]
+    fn test_catalog_positions() {
+        let json = r#"[{
+            "id": 1,
+            "name": "01_karelia",
+            "display_name": "Карелия",
+            "bounds": {"bottom_left": {"x": -500, "z": -500}, "upper_right": {"x": 500, "z": 500}},
+            "gameplays": {
+                "ctf": {
+                    "team_spawns": [[{"x": -400, "z": 400}], [{"x": 400, "z": -400}]],
+                    "team_bases": [[{"x": -350, "z": 350}], [{"x": 350, "z": -350}]]
+                }
+            }
+        }]"#;
+        let mut catalog = MapCatalog::bundled();
+        catalog.merge(MapCatalog::from_json(json.as_bytes()).unwrap());
+        assert_eq!(catalog.len(), MapCatalog::bundled().len());
+        let entry = catalog.by_name("01_karelia").unwrap();
+        assert_eq!(entry.display_name, "Карелия");
+        let positions = entry.positions(GameplayType::Ctf).unwrap();
+        assert_eq!(
+            positions.team_bases[1],
+            vec![MapPoint {
+                x: 350.0,
+                z: -350.0
+            }]
+        );
+        assert!(positions.control_points.is_empty());
+        assert_eq!(entry.positions(GameplayType::Domination), None);
+        assert!(matches!(
+            MapCatalog::load("maps.csv"),
+            Err(DataError::UnknownFormat(_))
+        ));
+    }
+}
@@ -0,0 +1,268 @@
+use crate::error::ReplayError;
+use crate::models::{ReaderLimit, ReaderLimits};
+use blowfish::cipher::generic_array::GenericArray;
+use blowfish::cipher::{BlockDecrypt, KeyInit};
+use blowfish::Blowfish;
+use byteorder::{ByteOrder, LittleEndian};
+use flate2::read::ZlibDecoder;
+use log::trace;
+use std::io::Read;
+
+/// The key the game client uses to encrypt the in-battle binary data
+pub const BLOWFISH_KEY: [u8; 16] = [
+    0xDE, 0x72, 0xBE, 0xA0, 0xDE, 0x04, 0xBE, 0xB1, 0xDE, 0xFE, 0xBE, 0xEF, 0xDE, 0xAD, 0xBE, 0xEF,
+];
+
+/// Size of the Blowfish block in bytes
+pub const BLOCK_SIZE: usize = 8;
+
+/// Size of the header preceding the encrypted data: the decompressed and the compressed sizes
+pub const HEADER_SIZE: usize = 8;
+
+/// Decrypts and decompresses the binary part of the replay (`RawReplay.replay`) and returns
+/// the plain packet stream. The default `ReaderLimits` are applied.
+pub fn unpack(replay: &[u8]) -> Result<Vec<u8>, ReplayError> {
+    unpack_with_limits(replay, &ReaderLimits::default())
+}
+
+/// Decrypts and decompresses the binary part of the replay refusing to decompress more than
+/// `ReaderLimits.max_stream_size` bytes.
+pub fn unpack_with_limits(replay: &[u8], limits: &ReaderLimits) -> Result<Vec<u8>, ReplayError> {
+    if replay.len() < HEADER_SIZE {
+        return Err(ReplayError::BattleStream(
+            "The binary part of the replay is too short",
+        ));
+    }
+    let decompressed_size = LittleEndian::read_u32(&replay[0..4]) as usize;
+    let compressed_size = LittleEndian::read_u32(&replay[4..8]) as usize;
+    trace!(
+        "unpacking battle stream: compressed {} bytes, decompressed {} bytes",
+        compressed_size,
+        decompressed_size
+    );
+    limits.check(ReaderLimit::StreamSize, decompressed_size as u64)?;
+    let decrypted = decrypt(&replay[HEADER_SIZE..])?;
+    if compressed_size > decrypted.len() {
+        return Err(ReplayError::BattleStream(
+            "The compressed battle stream is shorter than declared",
+        ));
+    }
+    let decompressed =
+        decompress_with_limit(&decrypted[..compressed_size], limits.max_stream_size)?;
+    if decompressed.len() != decompressed_size {
+        return Err(ReplayError::BattleStream(
+            "The decompressed battle stream size doesn't match the declared one",
+        ));
+    }
+    Ok(decompressed)
+}
+
+/// The battle stream recovered from the possibly truncated binary replay data
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecoveredStream {
+    /// The decompressed part of the battle stream
+    pub stream: Vec<u8>,
+    /// The binary replay data is truncated or corrupted
+    pub truncated: bool,
+}
+
+/// Decrypts and decompresses as much of the binary part of the replay as possible. Unlike
+/// `unpack` it doesn't fail on the truncated data, e.g. when the client crashed in the
+/// middle of the battle. The default `ReaderLimits` are applied.
+pub fn recover(replay: &[u8]) -> RecoveredStream {
+    recover_with_limits(replay, &ReaderLimits::default())
+}
+
+/// Recovers the battle stream decompressing no more than `ReaderLimits.max_stream_size`
+/// bytes. The stream cut by the limit is reported as truncated.
+pub fn recover_with_limits(replay: &[u8], limits: &ReaderLimits) -> RecoveredStream {
+    if replay.len() < HEADER_SIZE {
+        return RecoveredStream {
+            stream: vec![],
+            truncated: true,
+        };
+    }
+    let decompressed_size = LittleEndian::read_u32(&replay[0..4]) as usize;
+    let compressed_size = LittleEndian::read_u32(&replay[4..8]) as usize;
+    // The incomplete last block of the truncated data can't be decrypted, the padded block
+    // would be decompressed into garbage
+    let encrypted = &replay[HEADER_SIZE..];
+    let whole_blocks = encrypted.len() / BLOCK_SIZE * BLOCK_SIZE;
+    let decrypted = decrypt(&encrypted[..whole_blocks]).unwrap_or_default();
+    let compressed = &decrypted[..compressed_size.min(decrypted.len())];
+
+    let mut stream = Vec::new();
+    let mut decoder = ZlibDecoder::new(compressed);
+    let mut buf = [0u8; 8192];
+    let mut failed = false;
+    loop {
+        match decoder.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) if (stream.len() + n) as u64 > limits.max_stream_size => {
+                trace!("battle stream recovery stopped by the stream size limit");
+                failed = true;
+                break;
+            }
+            Ok(n) => stream.extend_from_slice(&buf[..n]),
+            Err(e) => {
+                trace!("battle stream decompression stopped: {}", e);
+                failed = true;
+                break;
+            }
+        }
+    }
+    let truncated = failed || stream.len() != decompressed_size;
+    RecoveredStream { stream, truncated }
+}
+
+/// Decrypts the data encrypted with Blowfish in ECB mode where every decrypted block is
+/// additionally XOR-ed with the previous decrypted block. The incomplete last block is
+/// padded with zeros.
+pub fn decrypt(data: &[u8]) -> Result<Vec<u8>, ReplayError> {
+    let cipher: Blowfish = Blowfish::new_from_slice(&BLOWFISH_KEY)
+        .map_err(|_| ReplayError::BattleStream("Invalid encryption key"))?;
+    let mut result = Vec::with_capacity(data.len() + BLOCK_SIZE);
+    let mut previous = [0u8; BLOCK_SIZE];
+    for chunk in data.chunks(BLOCK_SIZE) {
+        let mut padded = [0u8; BLOCK_SIZE];
+        padded[..chunk.len()].copy_from_slice(chunk);
+        let mut block = GenericArray::clone_from_slice(&padded);
+        cipher.decrypt_block(&mut block);
+        for (byte, prev) in block.iter_mut().zip(previous.iter()) {
+            *byte ^= prev;
+        }
+        previous.copy_from_slice(&block);
+        result.extend_from_slice(&block);
+    }
+    Ok(result)
+}
+
+/// Decompresses the zlib-compressed battle stream. The default `ReaderLimits` are applied.
+pub fn decompress(data: &[u8]) -> Result<Vec<u8>, ReplayError> {
+    decompress_with_limit(data, ReaderLimits::default().max_stream_size)
+}
+
+/// Decompresses the zlib-compressed battle stream refusing to produce more than `max_size`
+/// bytes
+pub fn decompress_with_limit(data: &[u8], max_size: u64) -> Result<Vec<u8>, ReplayError> {
+    let mut result = Vec::new();
+    ZlibDecoder::new(data)
+        .take(max_size.saturating_add(1))
+        .read_to_end(&mut result)
+        .map_err(ReplayError::Decompression)?;
+    if result.len() as u64 > max_size {
+        return Err(ReplayError::LimitExceeded {
+            limit: ReaderLimit::StreamSize,
+            value: result.len() as u64,
+            max: max_size,
+        });
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use blowfish::cipher::BlockEncrypt;
+    use flate2::write::ZlibEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+
+    /// Packs the plain battle stream the same way the game client does
+    pub(crate) fn pack(plain: &[u8]) -> Vec<u8> {
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(plain).unwrap();
+        let mut compressed = encoder.finish().unwrap();
+        let compressed_size = compressed.len();
+        compressed.resize(compressed_size.div_ceil(BLOCK_SIZE) * BLOCK_SIZE, 0);
+
+        let cipher: Blowfish = Blowfish::new_from_slice(&BLOWFISH_KEY).unwrap();
+        let mut result = vec![0u8; HEADER_SIZE];
+        LittleEndian::write_u32(&mut result[0..4], plain.len() as u32);
+        LittleEndian::write_u32(&mut result[4..8], compressed_size as u32);
+        let mut previous = [0u8; BLOCK_SIZE];
+        for chunk in compressed.chunks_exact(BLOCK_SIZE) {
+            let mut block = GenericArray::clone_from_slice(chunk);
+            for (byte, prev) in block.iter_mut().zip(previous.iter()) {
+                *byte ^= prev;
+            }
+            previous.copy_from_slice(chunk);
+            cipher.encrypt_block(&mut block);
+            result.extend_from_slice(&block);
+        }
+        result
+    }
+
+    #[test]
+    fn test_unpack() {
+        let plain: Vec<u8> = (0..10_000u32).map(|i| (i % 251) as u8).collect();
+        let packed = pack(&plain);
+        assert_eq!(unpack(&packed).unwrap(), plain);
+    }
+
+    #[test]
+    fn test_recover() {
+        let plain: Vec<u8> = (0..100_000u32).map(|i| (i * 7 % 251) as u8).collect();
+        let packed = pack(&plain);
+        assert_eq!(
+            recover(&packed),
+            RecoveredStream {
+                stream: plain.clone(),
+                truncated: false
+            }
+        );
+
+        let recovered = recover(&packed[..packed.len() / 2 + 3]);
+        assert!(recovered.truncated);
+        assert!(!recovered.stream.is_empty());
+        assert_eq!(recovered.stream[..], plain[..recovered.stream.len()]);
+    }
+
+    #[test]
+    fn test_unpack_invalid_data() {
+        assert!(unpack(&[0u8; 4]).is_err());
+        let mut packed = pack(b"battle stream");
+        packed.truncate(HEADER_SIZE);
+        assert!(unpack(&packed).is_err());
+    }
+
+    #[test]
+    fn test_decrypt_incomplete_block() {
+        let packed = pack(b"battle stream");
+        let encrypted = &packed[HEADER_SIZE..];
+        let decrypted = decrypt(encrypted).unwrap();
+        let padded = decrypt(&encrypted[..encrypted.len() - 3]).unwrap();
+        assert_eq!(padded.len(), decrypted.len());
+        let whole_blocks = decrypted.len() - BLOCK_SIZE;
+        assert_eq!(padded[..whole_blocks], decrypted[..whole_blocks]);
+    }
+
+    #[test]
+    fn test_stream_size_limit() {
+        let plain = vec![0u8; 100_000];
+        let packed = pack(&plain);
+        let limits = ReaderLimits {
+            max_stream_size: 1000,
+            ..ReaderLimits::default()
+        };
+        match unpack_with_limits(&packed, &limits) {
+            Err(ReplayError::LimitExceeded { limit, value, .. }) => {
+                assert_eq!(limit, ReaderLimit::StreamSize);
+                assert_eq!(value, plain.len() as u64);
+            }
+            r => panic!("Unexpected result: {:?}", r),
+        }
+        // The declared size can't be trusted, the decompressed data is limited too
+        let compressed = &decrypt(&packed[HEADER_SIZE..]).unwrap();
+        match decompress_with_limit(compressed, 1000) {
+            Err(ReplayError::LimitExceeded { value, max, .. }) => {
+                assert_eq!(value, 1001);
+                assert_eq!(max, 1000);
+            }
+            r => panic!("Unexpected result: {:?}", r),
+        }
+        let recovered = recover_with_limits(&packed, &limits);
+        assert!(recovered.truncated);
+        assert!(recovered.stream.len() <= 1000);
+    }
+}
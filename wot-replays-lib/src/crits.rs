@@ -0,0 +1,137 @@
+use serde::{Deserialize, Serialize};
+
+/// Vehicle modules of the critical hits. The discriminants are the bits of the packed
+/// module masks.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum VehicleModule {
+    Engine = 0,
+    AmmoBay = 1,
+    FuelTank = 2,
+    Radio = 3,
+    Track = 4,
+    Gun = 5,
+    TurretRotator = 6,
+    SurveyingDevice = 7,
+    /// Wheel of the wheeled vehicles
+    Wheel = 9,
+}
+
+impl VehicleModule {
+    pub const ALL: [VehicleModule; 9] = [
+        VehicleModule::Engine,
+        VehicleModule::AmmoBay,
+        VehicleModule::FuelTank,
+        VehicleModule::Radio,
+        VehicleModule::Track,
+        VehicleModule::Gun,
+        VehicleModule::TurretRotator,
+        VehicleModule::SurveyingDevice,
+        VehicleModule::Wheel,
+    ];
+}
+
+/// Crew roles of the critical hits. The discriminants are the bits of the packed crew mask.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CrewRole {
+    Commander = 0,
+    Driver = 1,
+    Radioman = 2,
+    Gunner = 3,
+    Loader = 4,
+}
+
+impl CrewRole {
+    pub const ALL: [CrewRole; 5] = [
+        CrewRole::Commander,
+        CrewRole::Driver,
+        CrewRole::Radioman,
+        CrewRole::Gunner,
+        CrewRole::Loader,
+    ];
+}
+
+/// Decoded `InteractionDetails.crits`.
+///
+/// The bits 0-11 are the damaged modules, the bits 12-23 are the destroyed modules and the
+/// bits 24-31 are the injured crew members.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct CriticalHits {
+    pub damaged_modules: Vec<VehicleModule>,
+    pub destroyed_modules: Vec<VehicleModule>,
+    pub injured_crew: Vec<CrewRole>,
+}
+
+impl CriticalHits {
+    pub fn from_packed(packed: u32) -> CriticalHits {
+        let modules = |mask: u32| {
+            VehicleModule::ALL
+                .iter()
+                .filter(|m| mask & (1 << **m as u32) != 0)
+                .copied()
+                .collect()
+        };
+        let crew = packed >> 24;
+        CriticalHits {
+            damaged_modules: modules(packed & 0xFFF),
+            destroyed_modules: modules((packed >> 12) & 0xFFF),
+            injured_crew: CrewRole::ALL
+                .iter()
+                .filter(|r| crew & (1 << **r as u32) != 0)
+                .copied()
+                .collect(),
+        }
+    }
+
+    pub fn packed(&self) -> u32 {
+        let mask = |modules: &[VehicleModule]| {
+            modules.iter().fold(0u32, |mask, m| mask | (1 << *m as u32))
+        };
+        let crew = self
+            .injured_crew
+            .iter()
+            .fold(0u32, |mask, r| mask | (1 << *r as u32));
+        mask(&self.damaged_modules) | (mask(&self.destroyed_modules) << 12) | (crew << 24)
+    }
+
+    /// Number of the damaged and destroyed modules
+    pub fn module_count(&self) -> usize {
+        self.damaged_modules.len() + self.destroyed_modules.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.module_count() == 0 && self.injured_crew.is_empty()
+    }
+}
+
+impl From<u32> for CriticalHits {
+    fn from(packed: u32) -> Self {
+        CriticalHits::from_packed(packed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_critical_hits() {
+        let crits = CriticalHits::from_packed(64);
+        assert_eq!(crits.damaged_modules, vec![VehicleModule::TurretRotator]);
+        assert!(crits.destroyed_modules.is_empty());
+        assert!(crits.injured_crew.is_empty());
+
+        let packed = (1 << 4) | (1 << 12) | (1 << 17) | (0b10010 << 24);
+        let crits = CriticalHits::from_packed(packed);
+        assert_eq!(
+            crits,
+            CriticalHits {
+                damaged_modules: vec![VehicleModule::Track],
+                destroyed_modules: vec![VehicleModule::Engine, VehicleModule::Gun],
+                injured_crew: vec![CrewRole::Driver, CrewRole::Loader],
+            }
+        );
+        assert_eq!(crits.module_count(), 3);
+        assert_eq!(crits.packed(), packed);
+        assert!(CriticalHits::from_packed(0).is_empty());
+    }
+}
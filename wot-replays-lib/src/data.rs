@@ -0,0 +1,207 @@
+use serde::de::DeserializeOwned;
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::Hash;
+use std::io;
+use std::path::Path;
+
+/// Errors of loading the data files: the vehicle database and the map catalog
+#[derive(Debug)]
+pub enum DataError {
+    Io(io::Error),
+    Json(serde_json::Error),
+    Csv(csv::Error),
+    /// The file extension is not supported by the loader
+    UnknownFormat(String),
+}
+
+impl fmt::Display for DataError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DataError::Io(e) => write!(f, "I/O error: {}", e),
+            DataError::Json(e) => write!(f, "Invalid data file json: {}", e),
+            DataError::Csv(e) => write!(f, "Invalid data file csv: {}", e),
+            DataError::UnknownFormat(path) => write!(f, "Unknown data file format: {}", path),
+        }
+    }
+}
+
+impl std::error::Error for DataError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            DataError::Io(e) => Some(e),
+            DataError::Json(e) => Some(e),
+            DataError::Csv(e) => Some(e),
+            DataError::UnknownFormat(_) => None,
+        }
+    }
+}
+
+impl From<io::Error> for DataError {
+    fn from(e: io::Error) -> Self {
+        DataError::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for DataError {
+    fn from(e: serde_json::Error) -> Self {
+        DataError::Json(e)
+    }
+}
+
+impl From<csv::Error> for DataError {
+    fn from(e: csv::Error) -> Self {
+        DataError::Csv(e)
+    }
+}
+
+/// Returns the lowercase extension of the data file
+pub(crate) fn extension(path: &Path) -> Option<String> {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase())
+}
+
+/// Entry of the `Table` with two unique keys
+pub(crate) trait Keyed {
+    type Key: Hash + Eq + Clone;
+    type AltKey: Hash + Eq + Clone;
+
+    fn key(&self) -> &Self::Key;
+
+    fn alt_key(&self) -> &Self::AltKey;
+}
+
+/// Entries indexed by two unique keys, e.g. the vehicle descriptor and tag
+#[derive(Debug, Clone)]
+pub(crate) struct Table<T: Keyed> {
+    entries: Vec<T>,
+    by_key: HashMap<T::Key, usize>,
+    by_alt_key: HashMap<T::AltKey, usize>,
+}
+
+impl<T: Keyed> Default for Table<T> {
+    fn default() -> Self {
+        Table {
+            entries: vec![],
+            by_key: HashMap::new(),
+            by_alt_key: HashMap::new(),
+        }
+    }
+}
+
+impl<T: Keyed> Table<T> {
+    /// Creates the table of the entries. The later entries override the earlier ones.
+    pub(crate) fn from_entries(entries: impl IntoIterator<Item = T>) -> Table<T> {
+        let mut table = Table::default();
+        for entry in entries {
+            table.insert(entry);
+        }
+        table
+    }
+
+    /// Loads the table from the JSON array of the entries
+    pub(crate) fn from_json(reader: impl io::Read) -> Result<Table<T>, DataError>
+    where
+        T: DeserializeOwned,
+    {
+        let entries: Vec<T> = serde_json::from_reader(reader)?;
+        Ok(Self::from_entries(entries))
+    }
+
+    /// Loads the snapshot bundled with the crate
+    pub(crate) fn bundled(snapshot: &str) -> Table<T>
+    where
+        T: DeserializeOwned,
+    {
+        Self::from_json(snapshot.as_bytes()).expect("The bundled snapshot is valid")
+    }
+
+    /// Adds the entry replacing the entries with the same keys. The entry may collide with
+    /// two different entries, one by each key, both are removed.
+    pub(crate) fn insert(&mut self, entry: T) {
+        let colliding: Vec<usize> = [
+            self.by_key.get(entry.key()),
+            self.by_alt_key.get(entry.alt_key()),
+        ]
+        .iter()
+        .flatten()
+        .map(|index| **index)
+        .collect();
+        if colliding.is_empty() {
+            self.by_key.insert(entry.key().clone(), self.entries.len());
+            self.by_alt_key
+                .insert(entry.alt_key().clone(), self.entries.len());
+            self.entries.push(entry);
+            return;
+        }
+        let mut index = 0;
+        self.entries.retain(|_| {
+            index += 1;
+            !colliding.contains(&(index - 1))
+        });
+        self.entries.push(entry);
+        self.by_key.clear();
+        self.by_alt_key.clear();
+        for (index, entry) in self.entries.iter().enumerate() {
+            self.by_key.insert(entry.key().clone(), index);
+            self.by_alt_key.insert(entry.alt_key().clone(), index);
+        }
+    }
+
+    /// Extends the table with the entries of another one
+    pub(crate) fn merge(&mut self, other: Table<T>) {
+        for entry in other.entries {
+            self.insert(entry);
+        }
+    }
+
+    pub(crate) fn get(&self, key: &T::Key) -> Option<&T> {
+        self.by_key.get(key).map(|index| &self.entries[*index])
+    }
+
+    pub(crate) fn get_by_alt_key(&self, key: &T::AltKey) -> Option<&T> {
+        self.by_alt_key.get(key).map(|index| &self.entries[*index])
+    }
+
+    pub(crate) fn entries(&self) -> &[T] {
+        &self.entries
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct Entry(u32, &'static str);
+
+    impl Keyed for Entry {
+        type Key = u32;
+        type AltKey = &'static str;
+
+        fn key(&self) -> &u32 {
+            &self.0
+        }
+
+        fn alt_key(&self) -> &&'static str {
+            &self.1
+        }
+    }
+
+    #[test]
+    fn test_table_insert() {
+        let mut table = Table::from_entries(vec![Entry(1, "a"), Entry(2, "b"), Entry(3, "c")]);
+        table.insert(Entry(3, "d"));
+        assert_eq!(table.entries().len(), 3);
+        assert_eq!(table.get_by_alt_key(&"c"), None);
+
+        // Collides with the first entry by key and with the second one by the other key
+        table.insert(Entry(1, "b"));
+        assert_eq!(table.entries(), &[Entry(3, "d"), Entry(1, "b")]);
+        assert_eq!(table.get(&1), Some(&Entry(1, "b")));
+        assert_eq!(table.get(&2), None);
+        assert_eq!(table.get_by_alt_key(&"a"), None);
+        assert_eq!(table.get_by_alt_key(&"d"), Some(&Entry(3, "d")));
+    }
+}
@@ -1,9 +1,95 @@
+use serde::{Deserialize, Serialize};
 use serde_repr::*;
+use std::convert::TryFrom;
+
+/// Declares the `u16` dictionary enum with the `Unknown` fallback variant. The enum is
+/// (de)serialized as its integer value.
+macro_rules! dictionary_with_unknown {
+    (
+        $(#[$meta:meta])*
+        pub enum $name:ident {
+            $($(#[$variant_meta:meta])* $variant:ident = $value:expr,)*
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Serialize, Deserialize, PartialEq, Eq, Hash, Debug, Clone, Copy)]
+        #[serde(from = "u16", into = "u16")]
+        pub enum $name {
+            $($(#[$variant_meta])* $variant,)*
+            Unknown(u16),
+        }
+
+        impl Default for $name {
+            fn default() -> Self {
+                $name::Unknown(0)
+            }
+        }
+
+        impl From<u16> for $name {
+            fn from(value: u16) -> Self {
+                match value {
+                    $($value => $name::$variant,)*
+                    v => $name::Unknown(v),
+                }
+            }
+        }
+
+        impl From<$name> for u16 {
+            fn from(value: $name) -> Self {
+                match value {
+                    $($name::$variant => $value,)*
+                    $name::Unknown(v) => v,
+                }
+            }
+        }
+    };
+}
+
+/// Declares the `u16` medal enum with the list of all its variants and the conversions from
+/// `u16` and into `Achievement`. The enum is (de)serialized as its integer value.
+macro_rules! medal_dictionary {
+    (
+        $(#[$meta:meta])*
+        pub enum $name:ident {
+            $($(#[$variant_meta:meta])* $variant:ident = $value:expr,)*
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Serialize_repr, Deserialize_repr, PartialEq, Eq, Hash, Debug, Clone, Copy)]
+        #[repr(u16)]
+        pub enum $name {
+            $($(#[$variant_meta])* $variant = $value,)*
+        }
+
+        impl $name {
+            pub const ALL: [$name; [$(stringify!($variant)),*].len()] = [$($name::$variant,)*];
+        }
+
+        impl TryFrom<u16> for $name {
+            type Error = u16;
+
+            fn try_from(value: u16) -> Result<Self, Self::Error> {
+                $name::ALL
+                    .iter()
+                    .find(|m| **m as u16 == value)
+                    .copied()
+                    .ok_or(value)
+            }
+        }
+
+        impl From<$name> for Achievement {
+            fn from(value: $name) -> Self {
+                Achievement::from(value as u16)
+            }
+        }
+    };
+}
 
 /// Contains possible arena finish reasons
-#[derive(Serialize_repr, Deserialize_repr, PartialEq, Debug)]
+#[derive(Serialize_repr, Deserialize_repr, PartialEq, Debug, Default)]
 #[repr(i8)]
 pub enum FinishReason {
+    #[default]
     Unknow = 0,
     AllVehicleDestroyed = 1,
     BaseCaptured = 2,
@@ -12,10 +98,89 @@ pub enum FinishReason {
     Technical = 5,
 }
 
+dictionary_with_unknown! {
+    /// Contains the arena bonus types (`CommonBattleInfo.bonus_type`, `BattleInfo.battle_type`)
+    pub enum BonusType {
+        Regular = 1,
+        Training = 2,
+        Company = 3,
+        Tournament = 4,
+        Clan = 5,
+        Tutorial = 6,
+        Cybersport = 7,
+        EventBattles = 9,
+        GlobalMap = 13,
+        TournamentRegular = 14,
+        TournamentClan = 15,
+        FalloutClassic = 18,
+        FalloutMultiteam = 19,
+        /// Skirmishes
+        Sortie2 = 20,
+        /// Advances (stronghold battles)
+        FortBattle2 = 21,
+        Ranked = 22,
+        Bootcamp = 23,
+        /// Grand Battles
+        EpicRandom = 24,
+        EpicRandomTraining = 25,
+        EventBattles2 = 26,
+        /// Frontline
+        EpicBattle = 27,
+        EpicBattleTraining = 28,
+        /// Steel Hunter (solo)
+        BattleRoyaleSolo = 29,
+        /// Steel Hunter (squad)
+        BattleRoyaleSquad = 30,
+        TournamentEvent = 31,
+        /// Blogger battles
+        Bob = 32,
+        EventRandom = 33,
+        BattleRoyaleTrainingSolo = 34,
+        BattleRoyaleTrainingSquad = 35,
+        WeekendBrawl = 36,
+        Mapbox = 37,
+        MapsTraining = 38,
+        /// Onslaught
+        Comp7 = 43,
+    }
+}
+
+dictionary_with_unknown! {
+    /// Contains the arena GUI types (`CommonBattleInfo.gui_type`)
+    pub enum GuiType {
+        Random = 1,
+        Training = 2,
+        Company = 3,
+        Tutorial = 4,
+        Cybersport = 5,
+        Fallout = 6,
+        EventBattles = 7,
+        RatedSandbox = 8,
+        Sandbox = 9,
+        FalloutClassic = 10,
+        FalloutMultiteam = 11,
+        Sortie2 = 12,
+        FortBattle2 = 13,
+        Ranked = 14,
+        Bootcamp = 15,
+        EpicRandom = 16,
+        EpicRandomTraining = 17,
+        EpicBattle = 18,
+        EpicTraining = 19,
+        BattleRoyale = 20,
+        Bob = 21,
+        EventRandom = 22,
+        Mapbox = 23,
+        MapsTraining = 24,
+        Comp7 = 28,
+    }
+}
+
 /// Contains possible vehicle death reasons
-#[derive(Serialize_repr, Deserialize_repr, PartialEq, Debug)]
+#[derive(Serialize_repr, Deserialize_repr, PartialEq, Debug, Clone, Copy, Default)]
 #[repr(i8)]
 pub enum DeathReason {
+    #[default]
     Alive = -1,
     Shot = 0,
     Fire = 1,
@@ -35,6 +200,93 @@ pub enum DeathReason {
     None = 15,
 }
 
+impl TryFrom<i8> for DeathReason {
+    type Error = i8;
+
+    fn try_from(value: i8) -> Result<Self, Self::Error> {
+        match value {
+            -1 => Ok(DeathReason::Alive),
+            0 => Ok(DeathReason::Shot),
+            1 => Ok(DeathReason::Fire),
+            2 => Ok(DeathReason::Rammin),
+            3 => Ok(DeathReason::WorldCollision),
+            4 => Ok(DeathReason::DeathZone),
+            5 => Ok(DeathReason::Drowning),
+            6 => Ok(DeathReason::GasAttack),
+            7 => Ok(DeathReason::Overturn),
+            8 => Ok(DeathReason::Manual),
+            9 => Ok(DeathReason::ArtillerProtection),
+            10 => Ok(DeathReason::ArtilerySector),
+            11 => Ok(DeathReason::Bombers),
+            12 => Ok(DeathReason::Recovery),
+            13 => Ok(DeathReason::ArtilleryEq),
+            14 => Ok(DeathReason::BomberEq),
+            15 => Ok(DeathReason::None),
+            v => Err(v),
+        }
+    }
+}
+
+/// Contains types of the personal battle events (`Avatar.onBattleEvents`)
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Copy)]
+pub enum BattleEventType {
+    Spotted,
+    RadioAssist,
+    TrackAssist,
+    BaseCapturePoints,
+    BaseCaptureDropped,
+    Tanking,
+    Crit,
+    Damage,
+    Kill,
+    ReceivedCrit,
+    ReceivedDamage,
+    StunAssist,
+    TargetVisibility,
+    Detected,
+    EnemySectorCaptured,
+    DestructibleDamaged,
+    DestructibleDestroyed,
+    DestructiblesDefended,
+    DefenderBonus,
+    SmokeAssist,
+    InspireAssist,
+    MultiStun,
+    EquipmentTimerExpired,
+    Unknown(u8),
+}
+
+impl From<u8> for BattleEventType {
+    fn from(value: u8) -> Self {
+        match value {
+            0 => BattleEventType::Spotted,
+            1 => BattleEventType::RadioAssist,
+            2 => BattleEventType::TrackAssist,
+            3 => BattleEventType::BaseCapturePoints,
+            4 => BattleEventType::BaseCaptureDropped,
+            5 => BattleEventType::Tanking,
+            6 => BattleEventType::Crit,
+            7 => BattleEventType::Damage,
+            8 => BattleEventType::Kill,
+            9 => BattleEventType::ReceivedCrit,
+            10 => BattleEventType::ReceivedDamage,
+            11 => BattleEventType::StunAssist,
+            12 => BattleEventType::TargetVisibility,
+            13 => BattleEventType::Detected,
+            14 => BattleEventType::EnemySectorCaptured,
+            15 => BattleEventType::DestructibleDamaged,
+            16 => BattleEventType::DestructibleDestroyed,
+            17 => BattleEventType::DestructiblesDefended,
+            18 => BattleEventType::DefenderBonus,
+            19 => BattleEventType::SmokeAssist,
+            20 => BattleEventType::InspireAssist,
+            21 => BattleEventType::MultiStun,
+            22 => BattleEventType::EquipmentTimerExpired,
+            v => BattleEventType::Unknown(v),
+        }
+    }
+}
+
 /// Contains mastery level badges
 pub enum MasterLevel {
     Third = 1,
@@ -52,58 +304,210 @@ pub enum PremType {
     None = 0,
 }
 
-pub enum BattleHeroMedal {
-    Invader = 35,
-    Sniper = 36,
-    Sniper2 = 227,
-    MainGun = 228,
-    Defender = 37,
-    Steelwall = 38,
-    Supporter = 39,
-    Scout = 40,
-    Evileye = 72,
-}
-
-pub enum EpicMedal {
-    MedalRadleyWalters = 73,
-    MedalLafayettePool = 74,
-    HeroesOfRassenay = 110,
-    MedalBillotte = 54,
-    MedalBrunoPietro = 75,
-    MedalTarczay = 76,
-    MedalBurda = 53,
-    MedalPascucci = 77,
-    MedalDumitru = 78,
-    MedalOskin = 51,
-    MedalHalonen = 52,
-    MedalKolobanov = 55,
-    MedalFadin = 56,
-    MedalDeLanglade = 145,
-    MedalGore = 298,
-    Huntsman = 148,
-    MedalTamadaYoshio = 146,
-    MedalStark = 300,
-}
-
-pub enum DossierAchivements {
-    FragBeast = 14,
-    FragsSinai = 108,
-    FragsPatton = 153,
-    Warrior = 34,
-    Invader = 35,
-    Sniper = 36,
-    Sniper2 = 227,
-    MainGun = 228,
-    Defender = 37,
-    Steelwall = 38,
-    Supporter = 39,
-    Scout = 40,
-    Evileye = 72,
-    BattleHeroes = 10,
-    SniperSiries = 23,
-    MaxSniperSiries = 24,
-    InvincibleSeries = 25,
-    MaxInvincibleSeries = 26,
-    DiehardSeries = 27,
-    MaxDiehardSeries = 28,
+medal_dictionary! {
+    /// Contains the battle hero medals
+    pub enum BattleHeroMedal {
+        Invader = 35,
+        Sniper = 36,
+        Sniper2 = 227,
+        MainGun = 228,
+        Defender = 37,
+        Steelwall = 38,
+        Supporter = 39,
+        Scout = 40,
+        Evileye = 72,
+    }
+}
+
+medal_dictionary! {
+    /// Contains the epic medals
+    pub enum EpicMedal {
+        MedalRadleyWalters = 73,
+        MedalLafayettePool = 74,
+        HeroesOfRassenay = 110,
+        MedalBillotte = 54,
+        MedalBrunoPietro = 75,
+        MedalTarczay = 76,
+        MedalBurda = 53,
+        MedalPascucci = 77,
+        MedalDumitru = 78,
+        MedalOrlik = 50,
+        MedalOskin = 51,
+        MedalHalonen = 52,
+        MedalKolobanov = 55,
+        MedalFadin = 56,
+        MedalDeLanglade = 145,
+        MedalGore = 298,
+        Huntsman = 148,
+        MedalTamadaYoshio = 146,
+        MedalStark = 300,
+        MedalLehvaslaiho = 70,
+        MedalNikolas = 71,
+        MedalBrothersInArms = 68,
+    }
+}
+
+medal_dictionary! {
+    /// Contains the dossier achievements
+    pub enum DossierAchivements {
+        FragBeast = 14,
+        FragsSinai = 108,
+        FragsPatton = 153,
+        Warrior = 34,
+        Invader = 35,
+        Sniper = 36,
+        Sniper2 = 227,
+        MainGun = 228,
+        Defender = 37,
+        Steelwall = 38,
+        Supporter = 39,
+        Scout = 40,
+        Evileye = 72,
+        BattleHeroes = 10,
+        SniperSiries = 23,
+        MaxSniperSiries = 24,
+        InvincibleSeries = 25,
+        MaxInvincibleSeries = 26,
+        DiehardSeries = 27,
+        MaxDiehardSeries = 28,
+    }
+}
+
+dictionary_with_unknown! {
+    /// Contains the achievements of `VehicleResult.achievements`
+    pub enum Achievement {
+        /// Top Gun
+        Warrior = 34,
+        Invader = 35,
+        Sniper = 36,
+        Sniper2 = 227,
+        MainGun = 228,
+        Defender = 37,
+        Steelwall = 38,
+        Supporter = 39,
+        Scout = 40,
+        Evileye = 72,
+        MedalRadleyWalters = 73,
+        MedalLafayettePool = 74,
+        HeroesOfRassenay = 110,
+        MedalBillotte = 54,
+        MedalBrunoPietro = 75,
+        MedalTarczay = 76,
+        MedalBurda = 53,
+        MedalPascucci = 77,
+        MedalDumitru = 78,
+        MedalOskin = 51,
+        MedalHalonen = 52,
+        MedalKolobanov = 55,
+        MedalFadin = 56,
+        MedalDeLanglade = 145,
+        MedalGore = 298,
+        Huntsman = 148,
+        MedalTamadaYoshio = 146,
+        MedalStark = 300,
+        MedalOrlik = 50,
+        MedalLehvaslaiho = 70,
+        MedalNikolas = 71,
+        MedalBrothersInArms = 68,
+        Raider = 61,
+        Kamikaze = 64,
+        Mousebane = 67,
+        Bombardier = 147,
+        Impenetrable = 112,
+        SniperSeries = 23,
+        MaxSniperSeries = 24,
+        InvincibleSeries = 25,
+        MaxInvincibleSeries = 26,
+        DiehardSeries = 27,
+        MaxDiehardSeries = 28,
+        /// Mastery badge, the value of the pop-up is the badge class
+        MarkOfMastery = 79,
+        BattleHeroes = 10,
+        FragBeast = 14,
+        FragsSinai = 108,
+        FragsPatton = 153,
+    }
+}
+
+/// Groups of the achievements
+#[derive(Serialize, Deserialize, PartialEq, Eq, Hash, Debug, Clone, Copy)]
+pub enum AchievementCategory {
+    BattleHero,
+    Epic,
+    Series,
+    Mastery,
+    Other,
+}
+
+impl Achievement {
+    pub fn category(&self) -> AchievementCategory {
+        let id = u16::from(*self);
+        if *self == Achievement::Warrior || BattleHeroMedal::try_from(id).is_ok() {
+            AchievementCategory::BattleHero
+        } else if EpicMedal::try_from(id).is_ok() {
+            AchievementCategory::Epic
+        } else if *self == Achievement::MarkOfMastery {
+            AchievementCategory::Mastery
+        } else if (23..=28).contains(&id) {
+            AchievementCategory::Series
+        } else {
+            AchievementCategory::Other
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_achievements() {
+        assert_eq!(BattleHeroMedal::try_from(72), Ok(BattleHeroMedal::Evileye));
+        assert_eq!(EpicMedal::try_from(72), Err(72));
+        assert_eq!(
+            DossierAchivements::try_from(25),
+            Ok(DossierAchivements::InvincibleSeries)
+        );
+        assert_eq!(
+            serde_json::to_string(&EpicMedal::MedalKolobanov).unwrap(),
+            "55"
+        );
+
+        assert_eq!(Achievement::from(72), Achievement::Evileye);
+        assert_eq!(
+            Achievement::from(BattleHeroMedal::Evileye),
+            Achievement::Evileye
+        );
+        assert_eq!(
+            Achievement::Evileye.category(),
+            AchievementCategory::BattleHero
+        );
+        assert_eq!(
+            Achievement::Warrior.category(),
+            AchievementCategory::BattleHero
+        );
+        assert_eq!(Achievement::from(55).category(), AchievementCategory::Epic);
+        assert_eq!(EpicMedal::try_from(71), Ok(EpicMedal::MedalNikolas));
+        assert_eq!(
+            Achievement::from(EpicMedal::MedalOrlik).category(),
+            AchievementCategory::Epic
+        );
+        assert_eq!(Achievement::from(64), Achievement::Kamikaze);
+        assert_eq!(Achievement::Kamikaze.category(), AchievementCategory::Other);
+        assert_eq!(
+            Achievement::MaxDiehardSeries.category(),
+            AchievementCategory::Series
+        );
+        assert_eq!(Achievement::from(9999), Achievement::Unknown(9999));
+        assert_eq!(
+            Achievement::Unknown(9999).category(),
+            AchievementCategory::Other
+        );
+        for m in DossierAchivements::ALL.iter() {
+            assert_ne!(Achievement::from(*m), Achievement::Unknown(*m as u16));
+        }
+        for m in EpicMedal::ALL.iter() {
+            assert_ne!(Achievement::from(*m), Achievement::Unknown(*m as u16));
+        }
+    }
 }
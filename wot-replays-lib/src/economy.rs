@@ -0,0 +1,807 @@
+use crate::data::DataError;
+use crate::models::PersonalVehicleResult;
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+use std::fmt;
+use std::io;
+use std::ops::Add;
+
+/// The bundled name table of the calculation steps
+const BUNDLED_NAMES: &str = include_str!("../data/value_replay_names.json");
+
+/// Size of the packed calculation step: operation, name index and `i32` value
+pub const PACKED_STEP_SIZE: usize = 6;
+
+/// Operations of the calculation steps
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Operation {
+    /// The value is set
+    Set,
+    Add,
+    Sub,
+    /// The value is multiplied by the integer
+    Mul,
+    /// The value is multiplied by the factor, the step value is the factor multiplied by 100
+    Factor,
+    /// The value is reduced by the coefficient multiplied by 100
+    SubCoeff,
+    /// The step marks the calculation stage and doesn't change the value
+    Tag,
+    Unknown(u8),
+}
+
+const OPERATION_NAMES: [(Operation, &str); 7] = [
+    (Operation::Set, "set"),
+    (Operation::Add, "add"),
+    (Operation::Sub, "sub"),
+    (Operation::Mul, "mul"),
+    (Operation::Factor, "factor"),
+    (Operation::SubCoeff, "subcoeff"),
+    (Operation::Tag, "tag"),
+];
+
+impl Operation {
+    pub fn name(&self) -> Option<&'static str> {
+        OPERATION_NAMES
+            .iter()
+            .find(|(o, _)| o == self)
+            .map(|(_, name)| *name)
+    }
+
+    fn from_name(name: &str) -> Option<Operation> {
+        OPERATION_NAMES
+            .iter()
+            .find(|(_, n)| n.eq_ignore_ascii_case(name))
+            .map(|(o, _)| *o)
+    }
+}
+
+impl From<u8> for Operation {
+    fn from(value: u8) -> Self {
+        OPERATION_NAMES
+            .get(value as usize)
+            .map(|(o, _)| *o)
+            .unwrap_or(Operation::Unknown(value))
+    }
+}
+
+/// What the calculation step is about, looked up by the step name
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StepCategory {
+    /// The value earned in the battle before any factors
+    Base,
+    /// Premium account factors
+    Premium,
+    Booster,
+    /// Personal reserves
+    Order,
+    Event,
+    /// Team damage penalties and compensations
+    Penalty,
+    Squad,
+    Referral,
+    Achievement,
+    /// The name is not one of the personal results values below
+    Other,
+}
+
+/// Categories of the personal results values the calculation steps use
+const STEP_CATEGORIES: [(&str, StepCategory); 60] = [
+    ("originalCredits", StepCategory::Base),
+    ("originalXP", StepCategory::Base),
+    ("originalFreeXP", StepCategory::Base),
+    ("originalTMenXP", StepCategory::Base),
+    ("originalGold", StepCategory::Base),
+    ("originalCrystal", StepCategory::Base),
+    ("originalBpcoin", StepCategory::Base),
+    ("originalEventCoin", StepCategory::Base),
+    ("appliedPremiumCreditsFactor100", StepCategory::Premium),
+    ("appliedPremiumXPFactor100", StepCategory::Premium),
+    ("appliedPremiumTmenXPFactor100", StepCategory::Premium),
+    ("premiumCreditsFactor100", StepCategory::Premium),
+    ("premiumXPFactor100", StepCategory::Premium),
+    ("premiumTmenXPFactor100", StepCategory::Premium),
+    ("premiumPlusCreditsFactor100", StepCategory::Premium),
+    ("premiumPlusXPFactor100", StepCategory::Premium),
+    ("premiumPlusTmenXPFactor100", StepCategory::Premium),
+    ("boosterCredits", StepCategory::Booster),
+    ("boosterCreditsFactor100", StepCategory::Booster),
+    ("boosterXP", StepCategory::Booster),
+    ("boosterXPFactor100", StepCategory::Booster),
+    ("boosterFreeXP", StepCategory::Booster),
+    ("boosterFreeXPFactor100", StepCategory::Booster),
+    ("boosterTMenXP", StepCategory::Booster),
+    ("boosterTMenXPFactor100", StepCategory::Booster),
+    ("orderCredits", StepCategory::Order),
+    ("orderCreditsFactor100", StepCategory::Order),
+    ("orderXP", StepCategory::Order),
+    ("orderXPFactor100", StepCategory::Order),
+    ("orderFreeXP", StepCategory::Order),
+    ("orderFreeXPFactor100", StepCategory::Order),
+    ("orderTMenXP", StepCategory::Order),
+    ("orderTMenXPFactor100", StepCategory::Order),
+    ("eventCredits", StepCategory::Event),
+    ("eventXP", StepCategory::Event),
+    ("eventFreeXP", StepCategory::Event),
+    ("eventTMenXP", StepCategory::Event),
+    ("eventGold", StepCategory::Event),
+    ("eventCrystal", StepCategory::Event),
+    ("eventBpcoin", StepCategory::Event),
+    ("eventEventCoin", StepCategory::Event),
+    ("creditsPenalty", StepCategory::Penalty),
+    ("xpPenalty", StepCategory::Penalty),
+    ("originalCreditsPenalty", StepCategory::Penalty),
+    ("originalXPPenalty", StepCategory::Penalty),
+    ("originalCreditsContributionIn", StepCategory::Penalty),
+    ("originalCreditsContributionOut", StepCategory::Penalty),
+    ("originalCreditsToDraw", StepCategory::Penalty),
+    ("squadXP", StepCategory::Squad),
+    ("squadXPFactor100", StepCategory::Squad),
+    ("premSquadCredits", StepCategory::Squad),
+    ("premSquadCreditsFactor100", StepCategory::Squad),
+    ("originalPremSquadCredits", StepCategory::Squad),
+    ("referral20Credits", StepCategory::Referral),
+    ("referral20CreditsFactor100", StepCategory::Referral),
+    ("referral20XP", StepCategory::Referral),
+    ("referral20XPFactor100", StepCategory::Referral),
+    ("achievementCredits", StepCategory::Achievement),
+    ("achievementXP", StepCategory::Achievement),
+    ("achievementFreeXP", StepCategory::Achievement),
+];
+
+/// A single step of the economy calculation
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct EconomyStep {
+    pub operation: Operation,
+    /// Name of the value the step uses, e.g. `appliedPremiumCreditsFactor100`
+    pub name: String,
+    /// Name index of the packed steps
+    pub name_index: Option<u8>,
+    /// Raw value of the step, the factors are multiplied by 100
+    pub value: i64,
+}
+
+impl EconomyStep {
+    /// Applies the step to the calculated value
+    pub fn apply(&self, value: i64) -> i64 {
+        match self.operation {
+            Operation::Set => self.value,
+            Operation::Add => value + self.value,
+            Operation::Sub => value - self.value,
+            Operation::Mul => value * self.value,
+            Operation::Factor => value * self.value / 100,
+            Operation::SubCoeff => value - value * self.value / 100,
+            Operation::Tag | Operation::Unknown(_) => value,
+        }
+    }
+
+    pub fn category(&self) -> StepCategory {
+        STEP_CATEGORIES
+            .iter()
+            .find(|(name, _)| *name == self.name)
+            .map(|(_, category)| *category)
+            .unwrap_or(StepCategory::Other)
+    }
+}
+
+/// The calculation logs of the personal results
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ValueReplayKind {
+    Credits,
+    Xp,
+    FreeXp,
+    TmenXp,
+    Crystal,
+    Gold,
+    Bpcoin,
+    EventCoin,
+    /// Frontline XP
+    FlXp,
+}
+
+/// Errors of decoding the calculation logs
+#[derive(Debug, Clone, PartialEq)]
+pub enum EconomyError {
+    /// The packed steps length is not a multiple of `PACKED_STEP_SIZE`
+    InvalidLength(usize),
+    /// The packed string has a character that is not a byte
+    InvalidByte(char),
+    /// The unpacked step is not a `[operation, name, value]` array
+    InvalidStep(usize),
+    /// The name index is out of the decoder names
+    UnknownNameIndex(u8),
+    /// The calculation log is neither a string nor an array
+    UnexpectedValue,
+}
+
+impl fmt::Display for EconomyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EconomyError::InvalidLength(length) => write!(
+                f,
+                "Packed calculation steps length {} is not a multiple of {}",
+                length, PACKED_STEP_SIZE
+            ),
+            EconomyError::InvalidByte(c) => {
+                write!(
+                    f,
+                    "Invalid character {:?} in the packed calculation steps",
+                    c
+                )
+            }
+            EconomyError::InvalidStep(index) => write!(f, "Invalid calculation step {}", index),
+            EconomyError::UnknownNameIndex(index) => {
+                write!(f, "Unknown calculation step name index {}", index)
+            }
+            EconomyError::UnexpectedValue => write!(f, "Unexpected calculation log value"),
+        }
+    }
+}
+
+impl std::error::Error for EconomyError {}
+
+/// Calculates the value of the steps, e.g. the credits of the `creditsReplay` steps
+pub fn calculate(steps: &[EconomyStep]) -> i64 {
+    steps.iter().fold(0, |value, step| step.apply(value))
+}
+
+/// Results with the calculation logs, the personal avatar and vehicle results
+pub trait ValueReplays {
+    /// Returns the calculation log of the kind, e.g. `credits_replay`
+    fn value_replay(&self, kind: ValueReplayKind) -> Option<&serde_json::Value>;
+
+    /// Decodes the calculation log of the kind. It's empty when the log is absent.
+    fn economy_steps(
+        &self,
+        kind: ValueReplayKind,
+        decoder: &ValueReplayDecoder,
+    ) -> Result<Vec<EconomyStep>, EconomyError> {
+        match self.value_replay(kind) {
+            Some(log) => decoder.decode(log),
+            None => Ok(vec![]),
+        }
+    }
+}
+
+/// Decoder of the calculation logs (`creditsReplay`, `xpReplay` and others).
+///
+/// The logs come either packed or unpacked. The packed log is a string of bytes (a char per
+/// byte) with a `PACKED_STEP_SIZE` bytes per step: the operation, the name index and the
+/// little endian `i32` value. The unpacked log is an array of `[operation, name, value]`
+/// steps, the operation is its name or id and the name is either the name or its index.
+///
+/// The name indexes are resolved with the name table. The bundled one lists the personal
+/// results values the calculations use, load the table of the game client with
+/// `ValueReplayDecoder::from_json` when its indexes differ.
+#[derive(Debug, Clone)]
+pub struct ValueReplayDecoder {
+    names: Vec<String>,
+}
+
+impl ValueReplayDecoder {
+    /// Creates the decoder resolving the name indexes with the names
+    pub fn new(names: Vec<String>) -> ValueReplayDecoder {
+        ValueReplayDecoder { names }
+    }
+
+    /// Creates the decoder with the bundled name table
+    pub fn bundled() -> ValueReplayDecoder {
+        Self::from_json(BUNDLED_NAMES.as_bytes()).expect("The bundled name table is valid")
+    }
+
+    /// Loads the name table from the JSON array of the names
+    pub fn from_json(reader: impl io::Read) -> Result<ValueReplayDecoder, DataError> {
+        Ok(Self::new(serde_json::from_reader(reader)?))
+    }
+
+    /// Decodes the calculation log. `null` is decoded as no steps.
+    pub fn decode(&self, log: &serde_json::Value) -> Result<Vec<EconomyStep>, EconomyError> {
+        match log {
+            serde_json::Value::Null => Ok(vec![]),
+            serde_json::Value::String(packed) => {
+                let bytes = packed
+                    .chars()
+                    .map(|c| u8::try_from(c as u32).map_err(|_| EconomyError::InvalidByte(c)))
+                    .collect::<Result<Vec<u8>, _>>()?;
+                self.decode_packed(&bytes)
+            }
+            serde_json::Value::Array(steps) => steps
+                .iter()
+                .enumerate()
+                .map(|(i, step)| self.decode_step(i, step))
+                .collect(),
+            _ => Err(EconomyError::UnexpectedValue),
+        }
+    }
+
+    pub fn decode_packed(&self, packed: &[u8]) -> Result<Vec<EconomyStep>, EconomyError> {
+        if !packed.len().is_multiple_of(PACKED_STEP_SIZE) {
+            return Err(EconomyError::InvalidLength(packed.len()));
+        }
+        packed
+            .chunks(PACKED_STEP_SIZE)
+            .map(|step| {
+                Ok(EconomyStep {
+                    operation: Operation::from(step[0]),
+                    name: self.name(step[1])?,
+                    name_index: Some(step[1]),
+                    value: i32::from_le_bytes([step[2], step[3], step[4], step[5]]) as i64,
+                })
+            })
+            .collect()
+    }
+
+    fn name(&self, index: u8) -> Result<String, EconomyError> {
+        self.names
+            .get(index as usize)
+            .cloned()
+            .ok_or(EconomyError::UnknownNameIndex(index))
+    }
+
+    fn decode_step(&self, i: usize, step: &serde_json::Value) -> Result<EconomyStep, EconomyError> {
+        let invalid = || EconomyError::InvalidStep(i);
+        let step = step
+            .as_array()
+            .filter(|s| s.len() == 3)
+            .ok_or_else(invalid)?;
+        let operation = match &step[0] {
+            serde_json::Value::String(name) => Operation::from_name(name),
+            v => v
+                .as_u64()
+                .and_then(|v| u8::try_from(v).ok())
+                .map(Operation::from),
+        }
+        .ok_or_else(invalid)?;
+        let (name, name_index) = match &step[1] {
+            serde_json::Value::String(name) => (name.clone(), None),
+            v => {
+                let index = v
+                    .as_u64()
+                    .and_then(|v| u8::try_from(v).ok())
+                    .ok_or_else(invalid)?;
+                (self.name(index)?, Some(index))
+            }
+        };
+        let value = step[2]
+            .as_i64()
+            .or_else(|| step[2].as_f64().map(|v| v.round() as i64))
+            .ok_or_else(invalid)?;
+        Ok(EconomyStep {
+            operation,
+            name,
+            name_index,
+            value,
+        })
+    }
+}
+
+/// The earned value with and without the premium account effect
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct EarnedValue {
+    /// The value the player got
+    pub value: i64,
+    pub without_premium: i64,
+    pub with_premium: i64,
+    /// Part of the value the boosters gave
+    pub booster: i64,
+    /// Part of the value the events and the missions gave, the premium account doesn't
+    /// change it
+    pub event: i64,
+}
+
+/// The parts of the earned value in the personal results, e.g. `originalCredits`,
+/// `subtotalCredits`, `boosterCredits` and `eventCredits`
+struct ValueParts {
+    value: i64,
+    /// The value before the factors
+    original: i64,
+    /// The original value with the premium account factor applied, if any
+    subtotal: i64,
+    booster: i64,
+    /// The other parts the premium account factor applies to, e.g. the premium vehicle XP
+    scaled: i64,
+    event: i64,
+}
+
+impl EarnedValue {
+    /// Separates the premium effect. The premium account factor turns the original value
+    /// into the subtotal and applies to the booster and the other scaled parts as well, the
+    /// rest of the value doesn't depend on the premium account. `premium_factor100` is the
+    /// factor the premium account gives when it's not applied.
+    fn new(parts: ValueParts, has_premium: bool, premium_factor100: u32) -> EarnedValue {
+        let scaled = parts.subtotal + parts.booster + parts.scaled;
+        let (without_premium, with_premium) = if !has_premium {
+            let premium = scaled * (premium_factor100.max(100) as i64 - 100) / 100;
+            (parts.value, parts.value + premium)
+        } else if parts.subtotal > 0 {
+            let premium = scaled - scaled * parts.original / parts.subtotal;
+            (parts.value - premium, parts.value)
+        } else {
+            (parts.value, parts.value)
+        };
+        EarnedValue {
+            value: parts.value,
+            without_premium,
+            with_premium,
+            booster: parts.booster,
+            event: parts.event,
+        }
+    }
+}
+
+impl Add for EarnedValue {
+    type Output = EarnedValue;
+
+    fn add(self, other: EarnedValue) -> EarnedValue {
+        EarnedValue {
+            value: self.value + other.value,
+            without_premium: self.without_premium + other.without_premium,
+            with_premium: self.with_premium + other.with_premium,
+            booster: self.booster + other.booster,
+            event: self.event + other.event,
+        }
+    }
+}
+
+/// Economics of the player who recorded the replay
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Economics {
+    /// Whether the premium account factors are applied
+    pub has_premium: bool,
+    /// Gross credits
+    pub credits: EarnedValue,
+    /// Vehicle repair cost (`repair`)
+    pub repair: i64,
+    /// Credits spent on the ammo (`autoLoadCost`)
+    pub ammo: i64,
+    /// Credits spent on the consumables (`autoEquipCost`)
+    pub consumables: i64,
+    /// Credits spent on the boosters (`autoEquipBoostersCost`), their contribution is
+    /// `EarnedValue::booster`
+    pub boosters: i64,
+    /// Gold spent on the ammo, the consumables and the boosters
+    pub gold_spent: i64,
+    /// Credits added to the piggy bank
+    pub piggy_bank: i64,
+    pub xp: EarnedValue,
+    pub free_xp: EarnedValue,
+    /// Crew XP
+    pub tmen_xp: EarnedValue,
+}
+
+impl Economics {
+    pub fn from_vehicle(vehicle: &PersonalVehicleResult) -> Economics {
+        let cost = |costs: &[i64], index: usize| costs.get(index).copied().unwrap_or(0);
+        let has_premium = vehicle.applied_premium_credits_factor100 > 100;
+        Economics {
+            has_premium,
+            credits: EarnedValue::new(
+                ValueParts {
+                    value: vehicle.stats.credits as i64,
+                    original: vehicle.original_credits,
+                    subtotal: vehicle.subtotal_credits,
+                    booster: vehicle.booster_credits,
+                    scaled: 0,
+                    event: vehicle.event_credits,
+                },
+                has_premium,
+                vehicle.premium_credits_factor100,
+            ),
+            repair: vehicle.repair,
+            ammo: cost(&vehicle.auto_load_cost, 0),
+            consumables: cost(&vehicle.auto_equip_cost, 0),
+            boosters: cost(&vehicle.auto_equip_boosters_cost, 0),
+            gold_spent: cost(&vehicle.auto_load_cost, 1)
+                + cost(&vehicle.auto_equip_cost, 1)
+                + cost(&vehicle.auto_equip_boosters_cost, 1),
+            piggy_bank: vehicle.piggy_bank,
+            xp: EarnedValue::new(
+                ValueParts {
+                    value: vehicle.stats.xp as i64,
+                    original: vehicle.original_xp as i64,
+                    subtotal: vehicle.subtotal_xp as i64,
+                    booster: vehicle.booster_xp as i64,
+                    scaled: vehicle.premium_vehicle_xp as i64,
+                    event: vehicle.event_xp as i64,
+                },
+                has_premium,
+                vehicle.premium_xp_factor100,
+            ),
+            // The results have no premium factor of the free XP, it's earned along with
+            // the XP
+            free_xp: EarnedValue::new(
+                ValueParts {
+                    value: vehicle.free_xp as i64,
+                    original: vehicle.original_free_xp as i64,
+                    subtotal: vehicle.subtotal_free_xp as i64,
+                    booster: vehicle.booster_free_xp as i64,
+                    scaled: 0,
+                    event: vehicle.event_free_xp as i64,
+                },
+                has_premium,
+                vehicle.premium_xp_factor100,
+            ),
+            tmen_xp: EarnedValue::new(
+                ValueParts {
+                    value: vehicle.tmen_xp as i64,
+                    original: vehicle.original_t_men_xp as i64,
+                    subtotal: vehicle.subtotal_t_men_xp as i64,
+                    booster: vehicle.booster_t_men_xp as i64,
+                    scaled: 0,
+                    event: vehicle.event_t_men_xp as i64,
+                },
+                has_premium,
+                vehicle.premium_tmen_xp_factor100,
+            ),
+        }
+    }
+
+    /// Credits spent on the repair, the ammo, the consumables and the boosters
+    pub fn expenses(&self) -> i64 {
+        self.repair + self.ammo + self.consumables + self.boosters
+    }
+
+    /// Gross credits minus the expenses
+    pub fn net_credits(&self) -> i64 {
+        self.credits.value - self.expenses()
+    }
+
+    /// Net credits the player would get without the premium account
+    pub fn net_credits_without_premium(&self) -> i64 {
+        self.credits.without_premium - self.expenses()
+    }
+
+    /// Net credits the player would get with the premium account
+    pub fn net_credits_with_premium(&self) -> i64 {
+        self.credits.with_premium - self.expenses()
+    }
+}
+
+impl Add for Economics {
+    type Output = Economics;
+
+    fn add(self, other: Economics) -> Economics {
+        Economics {
+            has_premium: self.has_premium || other.has_premium,
+            credits: self.credits + other.credits,
+            repair: self.repair + other.repair,
+            ammo: self.ammo + other.ammo,
+            consumables: self.consumables + other.consumables,
+            boosters: self.boosters + other.boosters,
+            gold_spent: self.gold_spent + other.gold_spent,
+            piggy_bank: self.piggy_bank + other.piggy_bank,
+            xp: self.xp + other.xp,
+            free_xp: self.free_xp + other.free_xp,
+            tmen_xp: self.tmen_xp + other.tmen_xp,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_decode_packed() {
+        let decoder = ValueReplayDecoder::from_json(
+            &br#"["originalCredits", "appliedPremiumCreditsFactor100", "creditsPenalty"]"#[..],
+        )
+        .unwrap();
+        let mut packed = vec![0u8, 0];
+        packed.extend_from_slice(&87318i32.to_le_bytes());
+        packed.extend_from_slice(&[4, 1]);
+        packed.extend_from_slice(&150i32.to_le_bytes());
+        packed.extend_from_slice(&[2, 2]);
+        packed.extend_from_slice(&(-5i32).to_le_bytes());
+        let log = json!(packed.iter().map(|b| *b as char).collect::<String>());
+        let steps = decoder.decode(&log).unwrap();
+        assert_eq!(steps.len(), 3);
+        assert_eq!(
+            steps[0],
+            EconomyStep {
+                operation: Operation::Set,
+                name: "originalCredits".to_string(),
+                name_index: Some(0),
+                value: 87318,
+            }
+        );
+        assert_eq!(steps[0].category(), StepCategory::Base);
+        assert_eq!(steps[1].operation, Operation::Factor);
+        assert_eq!(steps[1].category(), StepCategory::Premium);
+        assert_eq!(steps[2].operation, Operation::Sub);
+        assert_eq!(steps[2].value, -5);
+
+        packed.extend_from_slice(&[1, 9, 0, 0, 0, 0]);
+        assert_eq!(
+            decoder.decode_packed(&packed),
+            Err(EconomyError::UnknownNameIndex(9))
+        );
+        assert_eq!(
+            decoder.decode_packed(&packed[..5]),
+            Err(EconomyError::InvalidLength(5))
+        );
+        assert_eq!(
+            decoder.decode(&json!("\u{100}")),
+            Err(EconomyError::InvalidByte('\u{100}'))
+        );
+        assert_eq!(decoder.decode(&json!(null)), Ok(vec![]));
+    }
+
+    #[test]
+    fn test_economics() {
+        let mut vehicle = PersonalVehicleResult {
+            applied_premium_credits_factor100: 150,
+            premium_credits_factor100: 150,
+            original_credits: 80000,
+            subtotal_credits: 120000,
+            booster_credits: 60000,
+            event_credits: 10000,
+            original_xp: 1000,
+            subtotal_xp: 1500,
+            premium_vehicle_xp: 150,
+            event_xp: 1500,
+            original_free_xp: 50,
+            subtotal_free_xp: 75,
+            free_xp: 75,
+            repair: 3000,
+            auto_load_cost: vec![32000, 20],
+            auto_equip_cost: vec![20000, 0, 0],
+            auto_equip_boosters_cost: vec![30000, 0, 0],
+            ..PersonalVehicleResult::default()
+        };
+        vehicle.stats.credits = 190000;
+        vehicle.stats.xp = 3150;
+        let economics = Economics::from_vehicle(&vehicle);
+        assert!(economics.has_premium);
+        assert_eq!(
+            economics.credits,
+            EarnedValue {
+                value: 190000,
+                without_premium: 130000,
+                with_premium: 190000,
+                booster: 60000,
+                event: 10000,
+            }
+        );
+        assert_eq!(economics.boosters, 30000);
+        assert_eq!(economics.expenses(), 85000);
+        assert_eq!(economics.net_credits(), 105000);
+        assert_eq!(economics.net_credits_without_premium(), 45000);
+        assert_eq!(economics.gold_spent, 20);
+        // The event XP is flat, only the subtotal and the premium vehicle XP are scaled
+        assert_eq!(economics.xp.without_premium, 2600);
+        assert_eq!(economics.free_xp.without_premium, 50);
+        assert_eq!((economics + economics).net_credits(), 210000);
+
+        vehicle.applied_premium_credits_factor100 = 100;
+        vehicle.premium_xp_factor100 = 150;
+        vehicle.subtotal_xp = 1000;
+        vehicle.premium_vehicle_xp = 100;
+        vehicle.stats.xp = 2600;
+        let economics = Economics::from_vehicle(&vehicle);
+        assert!(!economics.has_premium);
+        assert_eq!(economics.xp.without_premium, 2600);
+        assert_eq!(economics.xp.with_premium, 3150);
+    }
+
+    #[test]
+    fn test_decode_unpacked() {
+        let decoder = ValueReplayDecoder::new(vec!["originalCredits".to_string()]);
+        let log = json!([
+            ["set", 0, 87318],
+            ["factor", "boosterCreditsFactor100", 50],
+            [2, "creditsPenalty", 100],
+            ["tag", "premiumVehicleXPFactor100", 0]
+        ]);
+        let steps = decoder.decode(&log).unwrap();
+        assert_eq!(steps[0].name, "originalCredits");
+        assert_eq!(steps[0].name_index, Some(0));
+        assert_eq!(steps[1].operation, Operation::Factor);
+        assert_eq!(steps[1].category(), StepCategory::Booster);
+        assert_eq!(steps[2].operation, Operation::Sub);
+        assert_eq!(steps[2].category(), StepCategory::Penalty);
+        assert_eq!(steps[3].category(), StepCategory::Other);
+        assert_eq!(
+            decoder.decode(&json!([["set", 1, 87318]])),
+            Err(EconomyError::UnknownNameIndex(1))
+        );
+        assert_eq!(
+            decoder.decode(&json!([["set", "originalCredits"]])),
+            Err(EconomyError::InvalidStep(0))
+        );
+        assert_eq!(
+            decoder.decode(&json!(1)),
+            Err(EconomyError::UnexpectedValue)
+        );
+    }
+
+    #[test]
+    fn test_value_replays_fixture() {
+        use crate::models::{DataChunk, Replay};
+        use std::convert::TryFrom;
+        use std::fs;
+
+        // The logs of the test battle are null, the fixture has them packed from the values
+        // of its results
+        let fixture: serde_json::Value =
+            serde_json::from_slice(&fs::read("test_data/value_replays.json").unwrap()).unwrap();
+        let mut raw_replay = crate::models::tests::build_raw_replay();
+        let mut results: serde_json::Value =
+            serde_json::from_slice(&raw_replay.data[1].payload).unwrap();
+        for (key, logs) in fixture.as_object().unwrap() {
+            let vehicle = results[0]["personal"][key].as_object_mut().unwrap();
+            for (name, log) in logs.as_object().unwrap() {
+                vehicle.insert(name.clone(), log.clone());
+            }
+        }
+        raw_replay.data[1] = DataChunk::new(serde_json::to_vec(&results).unwrap());
+
+        let replay = Replay::try_from(&raw_replay).unwrap();
+        let results = replay.results.unwrap();
+        let vehicle = results.personal().vehicles.values().next().unwrap();
+        let decoder = ValueReplayDecoder::bundled();
+
+        let steps = vehicle
+            .economy_steps(ValueReplayKind::Credits, &decoder)
+            .unwrap();
+        assert_eq!(steps.len(), 5);
+        assert_eq!(steps[0].name, "originalCredits");
+        assert_eq!(steps[0].value, vehicle.original_credits);
+        assert_eq!(steps[1].category(), StepCategory::Premium);
+        assert_eq!(steps[2].category(), StepCategory::Booster);
+        assert_eq!(steps[2].value, vehicle.booster_credits);
+        assert_eq!(calculate(&steps), vehicle.stats.credits as i64);
+        assert_eq!(calculate(&steps), 196466);
+
+        let steps = vehicle
+            .economy_steps(ValueReplayKind::Xp, &decoder)
+            .unwrap();
+        assert_eq!(steps[0].name, "originalXP");
+        assert_eq!(steps[3].name, "premiumVehicleXP");
+        assert_eq!(steps[4].category(), StepCategory::Event);
+        assert_eq!(calculate(&steps), vehicle.stats.xp as i64);
+        assert_eq!(calculate(&steps), 5562);
+        assert_eq!(
+            vehicle.economy_steps(ValueReplayKind::FreeXp, &decoder),
+            Ok(vec![])
+        );
+    }
+
+    #[test]
+    #[ignore = "needs the git-lfs content of test_data/*.wotreplay"]
+    fn test_value_replays_of_replay_files() {
+        use crate::models::{RawReplay, Replay};
+        use std::convert::TryFrom;
+
+        let kinds = [
+            ValueReplayKind::Credits,
+            ValueReplayKind::Xp,
+            ValueReplayKind::FreeXp,
+            ValueReplayKind::TmenXp,
+            ValueReplayKind::Crystal,
+            ValueReplayKind::Gold,
+            ValueReplayKind::Bpcoin,
+            ValueReplayKind::EventCoin,
+            ValueReplayKind::FlXp,
+        ];
+        let decoder = ValueReplayDecoder::bundled();
+        for (path, content) in crate::models::tests::test_data_replays() {
+            let raw_replay = RawReplay::read(&mut &content[..]).unwrap();
+            let results = match Replay::try_from(&raw_replay).unwrap().results {
+                Some(results) => results,
+                None => continue,
+            };
+            let personal = results.personal();
+            let mut logs: Vec<&dyn ValueReplays> = vec![&personal.avatar];
+            logs.extend(personal.vehicles.values().map(|v| v as &dyn ValueReplays));
+            for results in logs {
+                for kind in &kinds {
+                    if let Err(e) = results.economy_steps(*kind, &decoder) {
+                        panic!("{}: {:?} log: {}", path.display(), kind, e);
+                    }
+                }
+            }
+        }
+    }
+}
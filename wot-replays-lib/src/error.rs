@@ -0,0 +1,143 @@
+use crate::models::ReaderLimit;
+use crate::packets::PacketError;
+use std::fmt;
+use std::io;
+
+/// Errors of reading and parsing the replays
+#[derive(Debug)]
+pub enum ReplayError {
+    /// I/O error of the underlying stream
+    Io(io::Error),
+    /// The first 4 bytes of the file are not the replay magic
+    BadMagic([u8; 4]),
+    /// The stream ends in the middle of the data chunk
+    TruncatedChunk { index: u32, source: io::Error },
+    /// The replay exceeds the reader limit
+    LimitExceeded {
+        limit: ReaderLimit,
+        value: u64,
+        max: u64,
+    },
+    /// The data chunk is not a valid JSON or doesn't match the model
+    Json {
+        /// Index of the data chunk
+        chunk: usize,
+        /// Path to the invalid JSON value, e.g. `[0].personal.avatar.credits`
+        path: String,
+        source: serde_json::Error,
+    },
+    /// The data chunk doesn't match the model in the strict parse mode
+    Schema {
+        /// Index of the data chunk
+        chunk: usize,
+        /// Path to the JSON object, e.g. `[0].common`
+        path: String,
+        /// The model fields absent in the object
+        missing: Vec<String>,
+        /// The object keys the model doesn't know
+        unknown: Vec<String>,
+    },
+    /// There are no data chunks in the replay
+    MissingBattleInfo,
+    /// The replay declares the battle results chunk but it's absent
+    MissingResults { data_chunks: u32 },
+    /// The binary replay data can't be decrypted or decompressed
+    BattleStream(&'static str),
+    /// The battle stream can't be decompressed
+    Decompression(io::Error),
+    /// The battle stream packet can't be decoded
+    Packet(PacketError),
+    /// The replay can't be written, e.g. it's read without the binary replay data
+    Unwritable(&'static str),
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ReplayError::Io(e) => write!(f, "I/O error: {}", e),
+            ReplayError::BadMagic(magic) => write!(f, "Unknown file format, magic: {:02x?}", magic),
+            ReplayError::TruncatedChunk { index, source } => {
+                write!(f, "Truncated data chunk number {}: {}", index, source)
+            }
+            ReplayError::LimitExceeded { limit, value, max } => {
+                write!(f, "Reader limit {:?} exceeded: {} > {}", limit, value, max)
+            }
+            ReplayError::Json {
+                chunk,
+                path,
+                source,
+            } => write!(
+                f,
+                "Invalid json in data chunk number {} at `{}`: {}",
+                chunk, path, source
+            ),
+            ReplayError::Schema {
+                chunk,
+                path,
+                missing,
+                unknown,
+            } => write!(
+                f,
+                "Data chunk number {} doesn't match the schema at `{}`: missing fields {:?}, unknown fields {:?}",
+                chunk, path, missing, unknown
+            ),
+            ReplayError::MissingBattleInfo => write!(f, "No battle info data in the replay"),
+            ReplayError::MissingResults { data_chunks } => write!(
+                f,
+                "No battle results while there are {} data chunks declared",
+                data_chunks
+            ),
+            ReplayError::BattleStream(reason) => write!(f, "Invalid battle stream: {}", reason),
+            ReplayError::Decompression(e) => {
+                write!(f, "Battle stream decompression failed: {}", e)
+            }
+            ReplayError::Packet(e) => write!(f, "Invalid battle stream packet: {}", e),
+            ReplayError::Unwritable(reason) => write!(f, "The replay can't be written: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for ReplayError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ReplayError::Io(e) => Some(e),
+            ReplayError::TruncatedChunk { source, .. } => Some(source),
+            ReplayError::Json { source, .. } => Some(source),
+            ReplayError::Decompression(e) => Some(e),
+            ReplayError::Packet(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for ReplayError {
+    fn from(e: io::Error) -> Self {
+        ReplayError::Io(e)
+    }
+}
+
+impl From<PacketError> for ReplayError {
+    fn from(e: PacketError) -> Self {
+        ReplayError::Packet(e)
+    }
+}
+
+/// Deserializes the JSON data chunk keeping the path to the invalid value in the error
+pub(crate) fn from_json_chunk<'a, T: serde::Deserialize<'a>>(
+    chunk: usize,
+    payload: &'a [u8],
+) -> Result<T, ReplayError> {
+    let mut deserializer = serde_json::Deserializer::from_slice(payload);
+    let value =
+        serde_path_to_error::deserialize(&mut deserializer).map_err(|e| ReplayError::Json {
+            chunk,
+            path: e.path().to_string(),
+            source: e.into_inner(),
+        })?;
+    deserializer.end().map_err(|e| ReplayError::Json {
+        chunk,
+        path: ".".to_string(),
+        source: e,
+    })?;
+    Ok(value)
+}
@@ -1,11 +1,13 @@
+use crate::error::ReplayError;
 use crate::models;
 use std::convert::TryFrom;
 use std::fs::File;
+use std::io::Write;
 
 pub fn read_raw(
     mut stream: &mut impl std::io::Read,
     data_only: bool,
-) -> Result<models::RawReplay, Box<dyn std::error::Error>> {
+) -> Result<models::RawReplay, ReplayError> {
     if data_only {
         models::RawReplay::read_data_only(&mut stream)
     } else {
@@ -13,24 +15,49 @@ pub fn read_raw(
     }
 }
 
+pub fn read_raw_with_limits(
+    mut stream: &mut impl std::io::Read,
+    data_only: bool,
+    limits: &models::ReaderLimits,
+) -> Result<models::RawReplay, ReplayError> {
+    if data_only {
+        models::RawReplay::read_data_only_with_limits(&mut stream, limits)
+    } else {
+        models::RawReplay::read_with_limits(&mut stream, limits)
+    }
+}
+
 pub fn read_raw_from_file(
     file_name: &str,
     data_only: bool,
-) -> Result<models::RawReplay, Box<dyn std::error::Error>> {
+) -> Result<models::RawReplay, ReplayError> {
     let mut stream = File::open(file_name)?;
     read_raw(&mut stream, data_only)
 }
 
-pub fn read_and_parse(
-    mut stream: &mut impl std::io::Read,
-) -> Result<models::Replay, Box<dyn std::error::Error>> {
-    let raw_replay = read_raw(&mut stream, true)?;
-    Ok(models::Replay::try_from(&raw_replay)?)
+pub fn write_raw(
+    stream: &mut impl std::io::Write,
+    raw_replay: &models::RawReplay,
+) -> Result<(), ReplayError> {
+    raw_replay.write(stream)
 }
 
-pub fn read_and_parse_from_file(
+pub fn write_raw_to_file(
     file_name: &str,
-) -> Result<models::Replay, Box<dyn std::error::Error>> {
+    raw_replay: &models::RawReplay,
+) -> Result<(), ReplayError> {
+    let mut stream = std::io::BufWriter::new(File::create(file_name)?);
+    write_raw(&mut stream, raw_replay)?;
+    stream.flush()?;
+    Ok(())
+}
+
+pub fn read_and_parse(mut stream: &mut impl std::io::Read) -> Result<models::Replay, ReplayError> {
+    let raw_replay = read_raw(&mut stream, true)?;
+    models::Replay::try_from(&raw_replay)
+}
+
+pub fn read_and_parse_from_file(file_name: &str) -> Result<models::Replay, ReplayError> {
     let mut stream = File::open(file_name)?;
     read_and_parse(&mut stream)
 }
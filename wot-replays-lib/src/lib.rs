@@ -1,8 +1,19 @@
+pub mod arena;
+pub mod battle_stream;
+pub mod crits;
+pub mod data;
 pub mod dictionaries;
+pub mod economy;
+pub mod error;
 pub mod helpers;
 pub mod models;
+pub mod packets;
+pub mod vehicles;
+pub mod version;
 
+pub use error::ReplayError;
 pub use helpers::*;
+pub use version::ClientVersion;
 
 pub fn get_version() -> String {
     format!(
@@ -1,10 +1,15 @@
+use crate::economy::{ValueReplayKind, ValueReplays};
+use crate::error::{from_json_chunk, ReplayError};
+use crate::vehicles::{VehicleTag, VehicleTypeDescriptor};
+use crate::version::ClientVersion;
 use chrono::{DateTime, Utc};
 
-use byteorder::{ByteOrder, LittleEndian};
+use byteorder::{ByteOrder, LittleEndian, WriteBytesExt};
 use log::trace;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::convert::TryFrom;
+use std::io::Read;
 use std::str;
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -14,16 +19,138 @@ pub struct DataChunk {
 }
 
 impl DataChunk {
-    pub fn read<T: std::io::Read>(stream: &mut T) -> Result<DataChunk, Box<dyn std::error::Error>> {
+    pub fn new(payload: Vec<u8>) -> DataChunk {
+        DataChunk {
+            length: payload.len() as u32,
+            payload,
+        }
+    }
+
+    pub fn read<T: std::io::Read>(stream: &mut T) -> Result<DataChunk, ReplayError> {
+        Self::read_with_limit(stream, u32::MAX)
+    }
+
+    /// Reads the chunk refusing to read more than `max_size` bytes of the payload
+    pub fn read_with_limit<T: std::io::Read>(
+        stream: &mut T,
+        max_size: u32,
+    ) -> Result<DataChunk, ReplayError> {
+        let length = Self::read_length(stream)?;
+        if length > max_size {
+            return Err(ReplayError::LimitExceeded {
+                limit: ReaderLimit::ChunkSize,
+                value: length as u64,
+                max: max_size as u64,
+            });
+        }
+        Self::read_payload(stream, length)
+    }
+
+    fn read_length<T: std::io::Read>(stream: &mut T) -> Result<u32, ReplayError> {
         let mut length_buf = [0; 4];
         stream.read_exact(&mut length_buf)?;
-        let payload_length = LittleEndian::read_u32(&length_buf);
-        let mut payload_buf = vec![0u8; payload_length as usize];
-        stream.read_exact(&mut payload_buf)?;
-        Ok(DataChunk {
-            length: payload_length,
-            payload: payload_buf,
-        })
+        Ok(LittleEndian::read_u32(&length_buf))
+    }
+
+    /// Reads the payload of the declared length. The buffer grows with the data actually
+    /// read, so the declared length of the truncated stream doesn't lead to a huge allocation.
+    fn read_payload<T: std::io::Read>(
+        stream: &mut T,
+        length: u32,
+    ) -> Result<DataChunk, ReplayError> {
+        let mut payload = Vec::new();
+        stream.take(length as u64).read_to_end(&mut payload)?;
+        if payload.len() != length as usize {
+            return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into());
+        }
+        Ok(DataChunk { length, payload })
+    }
+
+    /// Replaces the payload updating the length
+    pub fn set_payload(&mut self, payload: Vec<u8>) {
+        self.length = payload.len() as u32;
+        self.payload = payload;
+    }
+
+    /// Writes the length-prefixed chunk. It fails if the payload doesn't fit the 32-bit
+    /// length or the length doesn't match the payload.
+    pub fn write<T: std::io::Write>(&self, stream: &mut T) -> Result<(), ReplayError> {
+        let length = u32::try_from(self.payload.len()).map_err(|_| ReplayError::LimitExceeded {
+            limit: ReaderLimit::ChunkSize,
+            value: self.payload.len() as u64,
+            max: u32::MAX as u64,
+        })?;
+        if length != self.length {
+            return Err(ReplayError::Unwritable(
+                "`DataChunk.length` doesn't match the payload, use `DataChunk::set_payload`",
+            ));
+        }
+        stream.write_u32::<LittleEndian>(length)?;
+        stream.write_all(&self.payload)?;
+        Ok(())
+    }
+}
+
+/// Kinds of the replay reader limits
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReaderLimit {
+    ChunkSize,
+    ChunkCount,
+    TotalSize,
+    /// Size of the decompressed battle stream
+    StreamSize,
+}
+
+/// Limits protecting the replay reader from corrupted or hostile files which declare huge
+/// chunk lengths or counts
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReaderLimits {
+    /// Maximum length of a single data chunk in bytes
+    pub max_chunk_size: u32,
+    /// Maximum number of the data chunks
+    pub max_chunk_count: u32,
+    /// Maximum size of the whole replay in bytes
+    pub max_total_size: u64,
+    /// Maximum size of the decompressed battle stream in bytes
+    pub max_stream_size: u64,
+}
+
+impl ReaderLimits {
+    /// No limits at all. Use it only for the trusted files.
+    pub fn unlimited() -> ReaderLimits {
+        ReaderLimits {
+            max_chunk_size: u32::MAX,
+            max_chunk_count: u32::MAX,
+            max_total_size: u64::MAX,
+            max_stream_size: u64::MAX,
+        }
+    }
+
+    pub(crate) fn check(&self, limit: ReaderLimit, value: u64) -> Result<(), ReplayError> {
+        let max = match limit {
+            ReaderLimit::ChunkSize => self.max_chunk_size as u64,
+            ReaderLimit::ChunkCount => self.max_chunk_count as u64,
+            ReaderLimit::TotalSize => self.max_total_size,
+            ReaderLimit::StreamSize => self.max_stream_size,
+        };
+        if value > max {
+            return Err(ReplayError::LimitExceeded { limit, value, max });
+        }
+        Ok(())
+    }
+}
+
+impl Default for ReaderLimits {
+    /// The limits are generous for the real replays: the JSON chunks are usually less than
+    /// 1 MiB, the whole file is less than 10 MiB and the battle stream is decompressed to
+    /// less than 50 MiB.
+    fn default() -> Self {
+        ReaderLimits {
+            max_chunk_size: 64 * 1024 * 1024,
+            max_chunk_count: 16,
+            max_total_size: 512 * 1024 * 1024,
+            max_stream_size: 512 * 1024 * 1024,
+        }
     }
 }
 
@@ -37,30 +164,56 @@ pub struct RawReplay {
     pub data: Vec<DataChunk>,
     /// The reamin part of the replay (the in-battle binary data)
     pub replay: Vec<u8>,
+    /// The replay is read with `read_data_only`, the binary replay data is not read
+    #[serde(default)]
+    pub data_only: bool,
 }
 
 impl RawReplay {
     /// Fully reads the replay stream including the binary replay data.
-    pub fn read<T: std::io::Read>(
+    /// The default `ReaderLimits` are applied.
+    pub fn read<T: std::io::Read>(stream: &mut T) -> Result<RawReplay, ReplayError> {
+        Self::read_with_limits(stream, &ReaderLimits::default())
+    }
+
+    /// Fully reads the replay stream including the binary replay data.
+    pub fn read_with_limits<T: std::io::Read>(
         mut stream: &mut T,
-    ) -> Result<RawReplay, Box<dyn std::error::Error>> {
-        let mut raw_replay = Self::read_data_only(&mut stream)?;
+        limits: &ReaderLimits,
+    ) -> Result<RawReplay, ReplayError> {
+        let mut raw_replay = Self::read_data_only_with_limits(&mut stream, limits)?;
+        let remain = limits
+            .max_total_size
+            .saturating_sub(raw_replay.data_size() as u64);
         let mut replay: Vec<u8> = Vec::new();
-        stream.read_to_end(&mut replay)?;
+        stream
+            .take(remain.saturating_add(1))
+            .read_to_end(&mut replay)?;
+        limits.check(
+            ReaderLimit::TotalSize,
+            (raw_replay.data_size() + replay.len()) as u64,
+        )?;
         raw_replay.replay = replay;
+        raw_replay.data_only = false;
         Ok(raw_replay)
     }
 
     /// Reads only the informational part of the replay. The reamin part will be ignored.
-    /// It's usefull for less memory footprint.
-    pub fn read_data_only<T: std::io::Read>(
+    /// It's usefull for less memory footprint. The default `ReaderLimits` are applied.
+    pub fn read_data_only<T: std::io::Read>(stream: &mut T) -> Result<RawReplay, ReplayError> {
+        Self::read_data_only_with_limits(stream, &ReaderLimits::default())
+    }
+
+    /// Reads only the informational part of the replay. The reamin part will be ignored.
+    pub fn read_data_only_with_limits<T: std::io::Read>(
         mut stream: &mut T,
-    ) -> Result<RawReplay, Box<dyn std::error::Error>> {
+        limits: &ReaderLimits,
+    ) -> Result<RawReplay, ReplayError> {
         let mut magic_buf = [0; 4];
         trace!("reading magic");
         stream.read_exact(&mut magic_buf)?;
         if magic_buf != [0x12, 0x32, 0x34, 0x11] {
-            return Err("Unknown file format".into());
+            return Err(ReplayError::BadMagic(magic_buf));
         }
         let magic = LittleEndian::read_u32(&magic_buf);
 
@@ -70,63 +223,425 @@ impl RawReplay {
         let data_chunks = LittleEndian::read_u32(&data_chunks_buf);
 
         trace!("Found {} data chunks", &data_chunks);
+        limits.check(ReaderLimit::ChunkCount, data_chunks as u64)?;
         let mut data = vec![];
+        let mut total_size = 8u64;
         for i in 0..data_chunks {
             trace!("reading data chunk number {}", i);
-            data.push(DataChunk::read(&mut stream)?);
+            let mut read_chunk = || {
+                let length = DataChunk::read_length(&mut stream)?;
+                limits.check(ReaderLimit::ChunkSize, length as u64)?;
+                // The limits are checked before reading the payload
+                limits.check(ReaderLimit::TotalSize, total_size + 4 + length as u64)?;
+                DataChunk::read_payload(&mut stream, length)
+            };
+            let chunk = read_chunk().map_err(|e| match e {
+                ReplayError::Io(source) if source.kind() == std::io::ErrorKind::UnexpectedEof => {
+                    ReplayError::TruncatedChunk { index: i, source }
+                }
+                e => e,
+            })?;
+            total_size += 4 + chunk.payload.len() as u64;
+            data.push(chunk);
         }
         Ok(RawReplay {
             magic,
             data_chunks,
             data,
             replay: vec![],
+            data_only: true,
+        })
+    }
+
+    /// Size of the magic, the number of data chunks and the length-prefixed chunks in bytes
+    fn data_size(&self) -> usize {
+        8 + self
+            .data
+            .iter()
+            .map(|chunk| 4 + chunk.payload.len())
+            .sum::<usize>()
+    }
+
+    /// Writes the replay: magic, number of data chunks, the chunks and the binary replay
+    /// data. The output is byte-for-byte identical to the input if nothing was changed.
+    ///
+    /// It refuses to write the replay read with `read_data_only`, the client can't play it
+    /// without the binary replay data. `data_chunks` must match the number of the chunks.
+    pub fn write<T: std::io::Write>(&self, stream: &mut T) -> Result<(), ReplayError> {
+        if self.data_only {
+            return Err(ReplayError::Unwritable(
+                "The binary replay data is not read, the replay is read with `read_data_only`",
+            ));
+        }
+        if self.data_chunks as usize != self.data.len() {
+            return Err(ReplayError::Unwritable(
+                "`data_chunks` doesn't match the number of the data chunks",
+            ));
+        }
+        stream.write_u32::<LittleEndian>(self.magic)?;
+        stream.write_u32::<LittleEndian>(self.data_chunks)?;
+        for (i, chunk) in self.data.iter().enumerate() {
+            trace!("writing data chunk number {}", i);
+            chunk.write(stream)?;
+        }
+        stream.write_all(&self.replay)?;
+        Ok(())
+    }
+
+    /// Decrypts and decompresses the binary replay data and returns the plain packet stream.
+    /// The replay must be read with `RawReplay::read`, otherwise the binary data is empty.
+    /// The default `ReaderLimits` are applied.
+    pub fn battle_stream(&self) -> Result<Vec<u8>, ReplayError> {
+        crate::battle_stream::unpack(&self.replay)
+    }
+
+    /// Decrypts and decompresses the binary replay data applying the `limits`
+    pub fn battle_stream_with_limits(&self, limits: &ReaderLimits) -> Result<Vec<u8>, ReplayError> {
+        crate::battle_stream::unpack_with_limits(&self.replay, limits)
+    }
+
+    /// Parses the client version from the battle info without parsing the whole chunk
+    pub fn client_version(&self) -> Option<ClientVersion> {
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct Versions {
+            client_version_from_xml: Option<String>,
+            client_version_from_exe: Option<String>,
+        }
+        let versions: Versions = serde_json::from_slice(&self.data.first()?.payload).ok()?;
+        parse_client_version(
+            versions.client_version_from_xml.as_deref(),
+            versions.client_version_from_exe.as_deref(),
+        )
+    }
+
+    /// Returns the packets of the battle stream for the client version of the replay
+    fn packets<'a>(&self, stream: &'a [u8]) -> crate::packets::Packets<'a> {
+        match self.client_version() {
+            Some(version) => crate::packets::Packets::with_version(stream, &version),
+            None => crate::packets::Packets::new(stream),
+        }
+    }
+
+    /// Extracts the in-battle chat messages from the binary replay data
+    pub fn chat(&self) -> Result<Vec<crate::packets::ChatMessage>, ReplayError> {
+        let stream = self.battle_stream()?;
+        let messages = self
+            .packets(&stream)
+            .chat_messages()
+            .collect::<Result<_, _>>()?;
+        Ok(messages)
+    }
+
+    /// Extracts the in-battle events (health changes, damage, kills, ...) from the binary
+    /// replay data using the entity definitions of the replay client version
+    pub fn battle_events(&self) -> Result<Vec<crate::packets::BattleEvent>, ReplayError> {
+        let definitions = match self.client_version() {
+            Some(version) => crate::packets::EntityDefinitions::for_version(&version),
+            None => crate::packets::EntityDefinitions::default(),
+        };
+        self.battle_events_with_definitions(definitions)
+    }
+
+    /// Extracts the in-battle events from the binary replay data using the custom entity
+    /// definitions
+    pub fn battle_events_with_definitions(
+        &self,
+        definitions: crate::packets::EntityDefinitions,
+    ) -> Result<Vec<crate::packets::BattleEvent>, ReplayError> {
+        let stream = self.battle_stream()?;
+        let events = self
+            .packets(&stream)
+            .battle_events(definitions)
+            .collect::<Result<_, _>>()?;
+        Ok(events)
+    }
+
+    /// Parses whatever can be recovered from the replay of the battle which the player left
+    /// early or where the client crashed. It fails only if the battle info is absent or
+    /// invalid. The absent or invalid battle results give `Completeness::BattleInfoOnly`.
+    pub fn recover(&self) -> Result<RecoveredReplay, ReplayError> {
+        let battle_info = Replay::parse_battle_info(self)?;
+        let (results, results_error) = match Replay::parse_results(self, ParseMode::Lenient) {
+            Ok(results) => (results, None),
+            Err(e) => {
+                trace!("battle results can't be recovered: {}", e);
+                (None, Some(e))
+            }
+        };
+        let replay = Replay {
+            battle_info,
+            results,
+        };
+        if self.data_only {
+            return Ok(RecoveredReplay {
+                completeness: if replay.results.is_none() {
+                    Completeness::BattleInfoOnly
+                } else {
+                    Completeness::BattleStreamNotRead
+                },
+                replay,
+                results_error,
+                battle_stream: vec![],
+                last_clock: None,
+            });
+        }
+        let recovered = crate::battle_stream::recover(&self.replay);
+        let mut truncated = recovered.truncated;
+        let mut last_clock = None;
+        for packet in self.packets(&recovered.stream) {
+            match packet {
+                Ok(p) => last_clock = Some(p.clock),
+                Err(e) => {
+                    trace!("battle stream recovery stopped: {}", e);
+                    truncated = true;
+                    break;
+                }
+            }
+        }
+        let completeness = if replay.results.is_none() {
+            Completeness::BattleInfoOnly
+        } else if truncated {
+            Completeness::TruncatedBattleStream
+        } else {
+            Completeness::Complete
+        };
+        Ok(RecoveredReplay {
+            completeness,
+            replay,
+            results_error,
+            battle_stream: recovered.stream,
+            last_clock,
         })
     }
 }
 
+/// Completeness of the replay
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum Completeness {
+    /// The replay contains the battle info, the battle results and the whole battle stream
+    Complete,
+    /// The replay contains only the battle info, e.g. the player left the battle before
+    /// the end. The battle stream may be truncated too.
+    BattleInfoOnly,
+    /// The replay contains the battle info and the battle results but the battle stream is
+    /// truncated or corrupted
+    TruncatedBattleStream,
+    /// The replay contains the battle info and the battle results, the battle stream is not
+    /// checked as the replay is read with `read_data_only`
+    BattleStreamNotRead,
+}
+
+/// The data recovered from the possibly incomplete replay
+pub struct RecoveredReplay {
+    pub completeness: Completeness,
+    pub replay: Replay,
+    /// The error of parsing the battle results, they are absent in `replay` then
+    pub results_error: Option<ReplayError>,
+    /// The decompressed part of the battle stream
+    pub battle_stream: Vec<u8>,
+    /// Game clock of the last complete packet of the battle stream
+    pub last_clock: Option<f32>,
+}
+
+/// How strictly the data chunks are matched against the models
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ParseMode {
+    /// The missing fields of `VehicleResult`, `PersonalAvatarResults` and
+    /// `CommonBattleInfo` get the default values and the unknown keys are kept in their
+    /// `extra` maps
+    #[default]
+    Lenient,
+    /// The missing and unknown fields are reported as `ReplayError::Schema`
+    Strict,
+}
+
 pub struct Replay {
     pub battle_info: BattleInfo,
     pub results: Option<BattleResults>,
 }
 
-impl TryFrom<&RawReplay> for Replay {
-    type Error = &'static str;
+impl Replay {
+    /// Parses the data chunks of the replay. `Replay::try_from` uses the lenient mode.
+    pub fn parse(raw_replay: &RawReplay, mode: ParseMode) -> Result<Replay, ReplayError> {
+        Ok(Self {
+            battle_info: Self::parse_battle_info(raw_replay)?,
+            results: Self::parse_results(raw_replay, mode)?,
+        })
+    }
 
-    fn try_from(raw_replay: &RawReplay) -> Result<Self, Self::Error> {
-        let raw_battle_info = if let Some(v) = &raw_replay.data.get(0) {
+    fn parse_battle_info(raw_replay: &RawReplay) -> Result<BattleInfo, ReplayError> {
+        let raw_battle_info = if let Some(v) = &raw_replay.data.first() {
             &v.payload
         } else {
             trace!("No battle info data in the replay");
-            return Err("No battle info data in the replay");
-        };
-        let battle_info: BattleInfo = match serde_json::from_slice(&raw_battle_info[..]) {
-            Ok(v) => v,
-            Err(e) => {
-                trace!("Invalid json: {}", e);
-                return Err("Invalid json");
-            }
+            return Err(ReplayError::MissingBattleInfo);
         };
-        let results = if raw_replay.data_chunks > 1 {
-            let raw_results = if let Some(v) = &raw_replay.data.get(1) {
-                &v.payload
-            } else {
-                return Err("Invalid replay format: no battle results while there are more than 1 data chunks declared");
-            };
-            let results: BattleResults = match serde_json::from_slice(&raw_results[..]) {
-                Ok(v) => v,
-                Err(e) => {
-                    println!("Invalid json: {}", e);
-                    return Err("Invalid json");
-                }
-            };
-            Some(results)
+        from_json_chunk(0, raw_battle_info)
+    }
+
+    fn parse_results(
+        raw_replay: &RawReplay,
+        mode: ParseMode,
+    ) -> Result<Option<BattleResults>, ReplayError> {
+        if raw_replay.data_chunks <= 1 {
+            return Ok(None);
+        }
+        let raw_results = if let Some(v) = &raw_replay.data.get(1) {
+            &v.payload
         } else {
-            None
+            return Err(ReplayError::MissingResults {
+                data_chunks: raw_replay.data_chunks,
+            });
         };
-        Ok(Self {
-            battle_info,
-            results,
-        })
+        let results: BattleResults = from_json_chunk(1, raw_results)?;
+        if mode == ParseMode::Strict {
+            check_results_schema(&results, raw_results)?;
+        }
+        Ok(Some(results))
+    }
+
+    /// Maps the vehicle tags of the battle info to the compact descriptors of the results.
+    /// Both are keyed by the vehicle id. It's empty for the replays without the results.
+    pub fn vehicle_descriptors(&self) -> HashMap<VehicleTag, VehicleTypeDescriptor> {
+        let mut result = HashMap::new();
+        let general = match &self.results {
+            Some(results) => &results.general,
+            None => return result,
+        };
+        for (vehicle_id, info) in &self.battle_info.vehicles {
+            let tag = info.vehicle_tag();
+            let vehicle_result = general.vehicles.get(vehicle_id).and_then(|v| v.first());
+            if let (Some(tag), Some(vehicle_result)) = (tag, vehicle_result) {
+                result.insert(tag, vehicle_result.vehicle_type());
+            }
+        }
+        result
+    }
+
+    /// Calculates the economics of the player who recorded the replay over the personal
+    /// vehicle results. It's `None` for the replays without the results.
+    pub fn economics(&self) -> Option<crate::economy::Economics> {
+        self.results
+            .as_ref()?
+            .personal()
+            .vehicles
+            .values()
+            .map(crate::economy::Economics::from_vehicle)
+            .reduce(|a, b| a + b)
+    }
+
+    /// Returns what the player who recorded the replay did to each other vehicle, ordered
+    /// by the target vehicle id. It's empty for the replays without the results.
+    pub fn interactions(&self) -> Vec<Interaction<'_>> {
+        let mut interactions: Vec<_> = self
+            .results
+            .iter()
+            .flat_map(|r| r.personal().vehicles.values())
+            .flat_map(|v| v.details.iter())
+            .map(|(key, details)| Interaction {
+                target_id: key.vehicle_id,
+                target: self.battle_info.vehicle(key.vehicle_id),
+                details,
+            })
+            .collect();
+        interactions.sort_by_key(|i| i.target_id);
+        interactions
+    }
+
+    /// Joins the battle info and the results into one entry per participant. The entries
+    /// are ordered by team and vehicle id. The results parts are `None` for the replays
+    /// without the results.
+    pub fn players(&self) -> Vec<BattlePlayer<'_>> {
+        let mut players: Vec<_> = self
+            .battle_info
+            .vehicles
+            .iter()
+            .filter_map(|(vehicle_id, info)| {
+                let vehicle_id = vehicle_id.parse().ok()?;
+                Some(self.player(vehicle_id, info))
+            })
+            .collect();
+        players.sort_by_key(|p| (p.team, p.vehicle_id));
+        players
+    }
+
+    fn player<'a>(&'a self, vehicle_id: u32, info: &'a VehicleInfo) -> BattlePlayer<'a> {
+        let results = self.results.as_ref();
+        let vehicle_results = results.and_then(|r| r.vehicle(vehicle_id));
+        let account_id = vehicle_results
+            .and_then(|v| v.first())
+            .map(|v| v.account_db_id);
+        let account_key = account_id.map(|id| id.to_string());
+        let account_key = account_key.as_ref();
+        let player_results = results.and_then(|r| r.player(vehicle_id));
+        BattlePlayer {
+            vehicle_id,
+            account_id,
+            name: &info.name,
+            fake_name: &info.fake_name,
+            clan: Some(info.clan_abbrev.as_str()).filter(|c| !c.is_empty()),
+            team: info.team,
+            vehicle: info.vehicle_tag(),
+            vehicle_type: vehicle_results
+                .and_then(|v| v.first())
+                .map(|v| v.vehicle_type()),
+            is_alive: player_results.map_or(info.is_alive != 0, |p| p.is_alive),
+            is_recording_player: match account_id {
+                Some(id) => id == self.battle_info.player_id,
+                None => info.name == self.battle_info.player_name,
+            },
+            frags: results.and_then(|r| r.frags_of(vehicle_id)),
+            info,
+            vehicle_results,
+            player_results,
+            avatar: results.and_then(|r| account_key.and_then(|k| r.general.avatars.get(k))),
+            player_info: results.and_then(|r| account_key.and_then(|k| r.general.players.get(k))),
+        }
+    }
+}
+
+/// Interaction of the player who recorded the replay with the target vehicle
+#[derive(Debug)]
+pub struct Interaction<'a> {
+    pub target_id: u32,
+    /// The target info, `None` when the battle info doesn't have the vehicle
+    pub target: Option<&'a VehicleInfo>,
+    pub details: &'a InteractionDetails,
+}
+
+/// A battle participant with the data of the battle info and the results resolved
+#[derive(Debug)]
+pub struct BattlePlayer<'a> {
+    /// Vehicle entity id, the key of `BattleInfo.vehicles`
+    pub vehicle_id: u32,
+    /// Account id, the key of `GeneralBattleResults.players` and `avatars`
+    pub account_id: Option<u64>,
+    pub name: &'a str,
+    /// The name shown to the other players in the anonymized battles
+    pub fake_name: &'a str,
+    pub clan: Option<&'a str>,
+    pub team: u8,
+    pub vehicle: Option<VehicleTag>,
+    pub vehicle_type: Option<VehicleTypeDescriptor>,
+    /// Survival at the battle end when the results are present
+    pub is_alive: bool,
+    /// Whether the player recorded the replay
+    pub is_recording_player: bool,
+    pub frags: Option<u8>,
+    pub info: &'a VehicleInfo,
+    /// Results per vehicle the player used in the battle
+    pub vehicle_results: Option<&'a [VehicleResult]>,
+    pub player_results: Option<&'a PlayerResults>,
+    pub avatar: Option<&'a AvatarResults>,
+    pub player_info: Option<&'a PlayerInfo>,
+}
+
+impl TryFrom<&RawReplay> for Replay {
+    type Error = ReplayError;
+
+    fn try_from(raw_replay: &RawReplay) -> Result<Self, Self::Error> {
+        Replay::parse(raw_replay, ParseMode::Lenient)
     }
 }
 
@@ -154,6 +669,13 @@ pub struct VehicleInfo {
     pub name: String,
 }
 
+impl VehicleInfo {
+    /// Parses the `vehicle_type`
+    pub fn vehicle_tag(&self) -> Option<VehicleTag> {
+        self.vehicle_type.parse().ok()
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct BattleInfo {
@@ -169,7 +691,7 @@ pub struct BattleInfo {
     pub server_settings: serde_json::Value,
     #[serde(rename = "gameplayID")]
     pub gameplay_id: String,
-    pub battle_type: u16,
+    pub battle_type: crate::dictionaries::BonusType,
     pub has_mods: bool,
     #[serde(with = "wot_date_format")]
     pub date_time: DateTime<Utc>,
@@ -180,6 +702,27 @@ pub struct BattleInfo {
     pub vehicles: HashMap<String, VehicleInfo>,
 }
 
+impl BattleInfo {
+    /// Parses the `player_vehicle`
+    pub fn player_vehicle_tag(&self) -> Option<VehicleTag> {
+        self.player_vehicle.parse().ok()
+    }
+
+    /// Parses the client version. The version from the executable is preferred, the
+    /// revision is taken from the version from the XML.
+    pub fn client_version(&self) -> Option<ClientVersion> {
+        parse_client_version(
+            Some(&self.client_version_from_xml),
+            Some(&self.client_version_from_exe),
+        )
+    }
+
+    /// Returns the vehicle info by the vehicle entity id, e.g. `PositionUpdate.entity_id`
+    pub fn vehicle(&self, entity_id: u32) -> Option<&VehicleInfo> {
+        self.vehicles.get(&entity_id.to_string())
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct AvatarResults {
@@ -195,27 +738,30 @@ pub struct AvatarResults {
     pub badges: Vec<Vec<u16>>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-#[serde(rename_all = "camelCase")]
+/// The fields added by the later game updates are `Option`s, they are `None` in the
+/// replays of the older clients. The other missing fields get the default values in the
+/// lenient mode.
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase", default)]
 pub struct PersonalAvatarResults {
     pub base_points_diff: u32,
     pub avatar_damage_dealt: u32,
-    pub bpcoin_replay: Option<u32>,
-    pub credits_replay: Option<u32>,
+    pub bpcoin_replay: Option<serde_json::Value>,
+    pub credits_replay: Option<serde_json::Value>,
     #[serde(rename = "freeXPReplay")]
-    pub free_xp_replay: Option<u32>,
+    pub free_xp_replay: Option<serde_json::Value>,
     pub sum_points: u32,
     pub fairplay_violations: Vec<i32>,
-    pub event_bpcoin: u32,
+    pub event_bpcoin: Option<u32>,
     pub badges: Vec<Vec<u16>>,
     pub active_rents: HashMap<String, u32>,
     #[serde(rename = "eventFreeXP")]
     pub event_free_xp: u32,
     pub event_credits: u32,
-    pub xp_replay: Option<u32>,
-    pub crystal: u32,
+    pub xp_replay: Option<serde_json::Value>,
+    pub crystal: Option<u32>,
     pub damage_event_list: Option<serde_json::Value>,
-    pub eligible_for_crystal_rewards: bool,
+    pub eligible_for_crystal_rewards: Option<bool>,
     pub dog_tags: serde_json::Value,
     pub is_premature_leave: bool,
     pub squad_bonus_info: Option<serde_json::Value>,
@@ -230,14 +776,14 @@ pub struct PersonalAvatarResults {
     pub avatar_damage_event_list: Option<serde_json::Value>,
     #[serde(rename = "PM2Progress")]
     pub pm2_progress: serde_json::Value,
-    pub has_battle_pass: bool,
+    pub has_battle_pass: Option<bool>,
     pub total_damaged: u16,
     pub gold_replay: Option<serde_json::Value>,
-    pub event_crystal: u16,
+    pub event_crystal: Option<u16>,
     pub event_gold: u32,
     #[serde(rename = "tmenXPReplay")]
     pub tmen_xp_replay: Option<serde_json::Value>,
-    pub event_coin_replai: Option<serde_json::Value>,
+    pub event_coin_replay: Option<serde_json::Value>,
     pub quests_progress: serde_json::Value,
     #[serde(rename = "accountDBID")]
     pub account_db_id: u64,
@@ -260,17 +806,23 @@ pub struct PersonalAvatarResults {
     #[serde(rename = "clanDBID")]
     pub clan_db_id: Option<u64>,
     pub credits: i64,
-    pub event_event_coin: u64,
-    pub watched_battle_to_the_end: bool,
+    pub event_event_coin: Option<u64>,
+    pub watched_battle_to_the_end: Option<bool>,
     #[serde(rename = "flXPReplay")]
     pub fl_xp_replay: Option<serde_json::Value>,
+    /// The keys the model doesn't know, e.g. added by a game update
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-#[serde(rename_all = "camelCase")]
+/// The fields added by the later game updates are `Option`s, they are `None` in the
+/// replays of the older clients. The other missing fields get the default values in the
+/// lenient mode.
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase", default)]
 pub struct VehicleResult {
     pub spotted: u8,
-    pub vehicle_num_captured: u16,
+    pub vehicle_num_captured: Option<u16>,
     pub damage_assisted_track: u32,
     pub xp_penalty: i32,
     pub direct_team_hits: u32,
@@ -279,8 +831,9 @@ pub struct VehicleResult {
     pub piercing_enemy_hits: u16,
     pub damage_assisted_radio: u32,
     pub mileage: u32,
-    pub stun_duration: f32,
+    pub stun_duration: Option<f32>,
     pub piercings: u16,
+    pub piercings_received: Option<u32>,
     pub damage_blocked_by_armor: u32,
     pub xp: u32,
     pub dropped_capture_points: u16,
@@ -296,7 +849,7 @@ pub struct VehicleResult {
     pub achievement_xp: u32,
     pub death_reason: crate::dictionaries::DeathReason,
     pub capture_points: u32,
-    pub num_recovered: u16,
+    pub num_recovered: Option<u16>,
     pub direct_enemy_hits: u32,
     pub max_health: u32,
     pub damage_event_list: Option<serde_json::Value>,
@@ -319,20 +872,20 @@ pub struct VehicleResult {
     pub life_time: u64,
     pub no_damage_direct_hits_received: u16,
     pub num_defended: u32,
-    pub stunned: u16,
-    pub equipment_damage_dealt: u32,
+    pub stunned: Option<u16>,
+    pub equipment_damage_dealt: Option<u32>,
     pub is_team_killer: bool,
     pub type_comp_descr: u32,
     pub solo_flag_capture: u32,
     pub destructibles_hits: u32,
     pub capturing_base: Option<serde_json::Value>,
-    pub damage_assisted_stun: u32,
-    pub rollouts_count: u32,
+    pub damage_assisted_stun: Option<u32>,
+    pub rollouts_count: Option<u32>,
     pub tkills: u16,
     pub potential_damage_received: u32,
     pub damage_dealt: u32,
     pub destructibles_num_destroyed: u32,
-    pub damage_assisted_smoke: u32,
+    pub damage_assisted_smoke: Option<u32>,
     pub destructibles_damage_dealt: u32,
     pub flag_actions: [u32; 4],
     pub win_points: u32,
@@ -341,17 +894,454 @@ pub struct VehicleResult {
     #[serde(rename = "xp/attack")]
     pub xp_attack: u32,
     pub tdestroyed_modules: u32,
-    pub stun_num: u32,
-    pub damage_assisted_inspire: u32,
+    pub stun_num: Option<u32>,
+    pub damage_assisted_inspire: Option<u32>,
     #[serde(rename = "achievementFreeXP")]
     pub achievement_free_xp: u32,
     pub direct_hits: u32,
+    /// The keys the model doesn't know, e.g. added by a game update
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-#[serde(rename_all = "camelCase")]
+impl VehicleResult {
+    /// Returns the decoded `type_comp_descr`
+    pub fn vehicle_type(&self) -> VehicleTypeDescriptor {
+        VehicleTypeDescriptor::from_packed(self.type_comp_descr)
+    }
+
+    /// Returns the typed `achievements`
+    pub fn achievements(&self) -> Vec<crate::dictionaries::Achievement> {
+        self.achievements
+            .iter()
+            .map(|a| crate::dictionaries::Achievement::from(*a))
+            .collect()
+    }
+}
+
+/// Results of the vehicle the player who recorded the replay used in the battle
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PersonalVehicleResult {
+    /// The battle stats the same as in `GeneralBattleResults.vehicles`. The keys the model
+    /// doesn't know are kept in its `extra` map.
+    #[serde(flatten)]
+    pub stats: VehicleResult,
+    #[serde(rename = "additionalXPFactor10")]
+    pub additional_xp_factor10: u32,
+    pub aogas_factor10: u32,
+    /// The applied premium account credits factor multiplied by 100
+    pub applied_premium_credits_factor100: u32,
+    /// The applied premium account crew XP factor multiplied by 100
+    #[serde(rename = "appliedPremiumTmenXPFactor100")]
+    pub applied_premium_tmen_xp_factor100: u32,
+    /// The applied premium account XP factor multiplied by 100
+    #[serde(rename = "appliedPremiumXPFactor100")]
+    pub applied_premium_xp_factor100: u32,
+    /// Cost of the automatically restocked boosters: credits, gold and crystal
+    pub auto_equip_boosters_cost: Vec<i64>,
+    /// Cost of the automatically restocked consumables: credits, gold and crystal
+    pub auto_equip_cost: Vec<i64>,
+    /// Cost of the automatically reloaded ammo: credits and gold
+    pub auto_load_cost: Vec<i64>,
+    /// Cost of the automatic repair
+    pub auto_repair_cost: i64,
+    pub avatar_damage_event_list: Option<serde_json::Value>,
+    /// Number of the battles played on the vehicle
+    pub battle_num: u32,
+    pub booster_credits: i64,
+    pub booster_credits_factor100: u32,
+    #[serde(rename = "boosterFreeXP")]
+    pub booster_free_xp: u32,
+    #[serde(rename = "boosterFreeXPFactor100")]
+    pub booster_free_xp_factor100: u32,
+    #[serde(rename = "boosterTMenXP")]
+    pub booster_t_men_xp: u32,
+    #[serde(rename = "boosterTMenXPFactor100")]
+    pub booster_t_men_xp_factor100: u32,
+    #[serde(rename = "boosterXP")]
+    pub booster_xp: u32,
+    #[serde(rename = "boosterXPFactor100")]
+    pub booster_xp_factor100: u32,
+    pub bpcoin: i64,
+    pub bpcoin_replay: Option<serde_json::Value>,
+    pub c11n_progress: serde_json::Value,
+    pub committed_suicide: bool,
+    pub credits_contribution_in: i64,
+    pub credits_contribution_out: i64,
+    pub credits_penalty: i64,
+    pub credits_replay: Option<serde_json::Value>,
+    pub credits_to_draw: i64,
+    pub crystal: i64,
+    pub crystal_replay: Option<serde_json::Value>,
+    #[serde(rename = "dailyXPFactor10")]
+    pub daily_xp_factor10: u32,
+    pub damage_before_team_was_damaged: u32,
+    pub damaged_while_enemy_moving: u32,
+    pub damaged_while_moving: u32,
+    pub damage_rating: u32,
+    /// Interactions with the other vehicles
+    pub details: HashMap<InteractionKey, InteractionDetails>,
+    /// Achievements and records shown after the battle: id and value
+    pub dossier_pop_ups: Vec<(u16, serde_json::Value)>,
+    pub event_bpcoin: i64,
+    pub event_bpcoin_list: Vec<serde_json::Value>,
+    pub event_coin: i64,
+    pub event_coin_replay: Option<serde_json::Value>,
+    pub event_credits: i64,
+    pub event_credits_factor100_list: Vec<(String, u32)>,
+    pub event_credits_list: Vec<serde_json::Value>,
+    pub event_crystal: i64,
+    pub event_crystal_list: Vec<serde_json::Value>,
+    pub event_event_coin: i64,
+    pub event_event_coin_list: Vec<serde_json::Value>,
+    #[serde(rename = "eventFreeXP")]
+    pub event_free_xp: u32,
+    #[serde(rename = "eventFreeXPFactor100List")]
+    pub event_free_xp_factor100_list: Vec<(String, u32)>,
+    #[serde(rename = "eventFreeXPList")]
+    pub event_free_xp_list: Vec<serde_json::Value>,
+    pub event_gold: i64,
+    pub event_gold_factor100_list: Vec<(String, u32)>,
+    pub event_gold_list: Vec<serde_json::Value>,
+    #[serde(rename = "eventTMenXP")]
+    pub event_t_men_xp: u32,
+    #[serde(rename = "eventTMenXPFactor100List")]
+    pub event_t_men_xp_factor100_list: Vec<(String, u32)>,
+    #[serde(rename = "eventTMenXPList")]
+    pub event_t_men_xp_list: Vec<serde_json::Value>,
+    #[serde(rename = "eventXP")]
+    pub event_xp: u32,
+    #[serde(rename = "eventXPFactor100List")]
+    pub event_xp_factor100_list: Vec<(String, u32)>,
+    #[serde(rename = "eventXPList")]
+    pub event_xp_list: Vec<serde_json::Value>,
+    /// Credits earned in the battle with all the factors applied
+    pub factual_credits: i64,
+    #[serde(rename = "factualFreeXP")]
+    pub factual_free_xp: u32,
+    #[serde(rename = "factualXP")]
+    pub factual_xp: u32,
+    pub fairplay_factor10: u32,
+    #[serde(rename = "flXPReplay")]
+    pub fl_xp_replay: Option<serde_json::Value>,
+    #[serde(rename = "freeXP")]
+    pub free_xp: u32,
+    #[serde(rename = "freeXPReplay")]
+    pub free_xp_replay: Option<serde_json::Value>,
+    pub gold: i64,
+    pub gold_replay: Option<serde_json::Value>,
+    #[serde(rename = "igrXPFactor10")]
+    pub igr_xp_factor10: u32,
+    pub is_premium: bool,
+    pub killed_and_damaged_by_all_squadmates: u32,
+    pub kills_before_team_was_damaged: u32,
+    /// Mastery badge got in the battle
+    pub mark_of_mastery: u32,
+    /// Marks of excellence after the battle
+    pub marks_on_gun: u32,
+    /// Moving average of the combined damage the marks of excellence are based on
+    pub moving_avg_damage: u32,
+    pub order_credits: i64,
+    pub order_credits_factor100: u32,
+    #[serde(rename = "orderFreeXP")]
+    pub order_free_xp: u32,
+    #[serde(rename = "orderFreeXPFactor100")]
+    pub order_free_xp_factor100: u32,
+    #[serde(rename = "orderTMenXP")]
+    pub order_t_men_xp: u32,
+    #[serde(rename = "orderTMenXPFactor100")]
+    pub order_t_men_xp_factor100: u32,
+    #[serde(rename = "orderXP")]
+    pub order_xp: u32,
+    #[serde(rename = "orderXPFactor100")]
+    pub order_xp_factor100: u32,
+    pub original_bpcoin: i64,
+    /// Credits earned in the battle before any factors are applied
+    pub original_credits: i64,
+    pub original_credits_contribution_in: i64,
+    pub original_credits_contribution_in_squad: i64,
+    pub original_credits_contribution_out: i64,
+    pub original_credits_contribution_out_squad: i64,
+    pub original_credits_penalty: i64,
+    pub original_credits_penalty_squad: i64,
+    pub original_credits_to_draw: i64,
+    pub original_credits_to_draw_squad: i64,
+    pub original_crystal: i64,
+    pub original_event_coin: i64,
+    #[serde(rename = "originalFreeXP")]
+    pub original_free_xp: u32,
+    pub original_gold: i64,
+    pub original_prem_squad_credits: i64,
+    #[serde(rename = "originalTMenXP")]
+    pub original_t_men_xp: u32,
+    #[serde(rename = "originalXP")]
+    pub original_xp: u32,
+    #[serde(rename = "originalXPPenalty")]
+    pub original_xp_penalty: i32,
+    pub percent_from_second_best_damage: f32,
+    pub percent_from_total_team_damage: f32,
+    /// Credits added to the piggy bank of the premium account
+    pub piggy_bank: i64,
+    #[serde(rename = "playerRankXP")]
+    pub player_rank_xp: u32,
+    #[serde(rename = "playerRankXPFactor100")]
+    pub player_rank_xp_factor100: u32,
+    pub premium_credits_factor100: u32,
+    pub premium_plus_credits_factor100: u32,
+    #[serde(rename = "premiumPlusTmenXPFactor100")]
+    pub premium_plus_tmen_xp_factor100: u32,
+    #[serde(rename = "premiumPlusXPFactor100")]
+    pub premium_plus_xp_factor100: u32,
+    #[serde(rename = "premiumTmenXPFactor100")]
+    pub premium_tmen_xp_factor100: u32,
+    #[serde(rename = "premiumVehicleXP")]
+    pub premium_vehicle_xp: u32,
+    #[serde(rename = "premiumVehicleXPFactor100")]
+    pub premium_vehicle_xp_factor100: u32,
+    #[serde(rename = "premiumXPFactor100")]
+    pub premium_xp_factor100: u32,
+    /// Bit mask of the active premium account types
+    pub prem_mask: u32,
+    pub prem_squad_credits: i64,
+    pub prem_squad_credits_factor100: u32,
+    /// The best mastery badge before the battle
+    pub prev_mark_of_mastery: u32,
+    pub quests_progress: serde_json::Value,
+    pub referral20_credits: i64,
+    pub referral20_credits_factor100: u32,
+    #[serde(rename = "referral20XP")]
+    pub referral20_xp: u32,
+    #[serde(rename = "referral20XPFactor100")]
+    pub referral20_xp_factor100: u32,
+    #[serde(rename = "refSystemXPFactor10")]
+    pub ref_system_xp_factor10: u32,
+    /// Cost of the vehicle repair
+    pub repair: i64,
+    #[serde(rename = "serviceProviderID")]
+    pub service_provider_id: u32,
+    #[serde(rename = "squadXP")]
+    pub squad_xp: u32,
+    #[serde(rename = "squadXPFactor100")]
+    pub squad_xp_factor100: u32,
+    pub subtotal_bpcoin: i64,
+    pub subtotal_credits: i64,
+    pub subtotal_crystal: i64,
+    pub subtotal_event_coin: i64,
+    #[serde(rename = "subtotalFreeXP")]
+    pub subtotal_free_xp: u32,
+    pub subtotal_gold: i64,
+    #[serde(rename = "subtotalTMenXP")]
+    pub subtotal_t_men_xp: u32,
+    #[serde(rename = "subtotalXP")]
+    pub subtotal_xp: u32,
+    #[serde(rename = "tmenXP")]
+    pub tmen_xp: u32,
+    #[serde(rename = "tmenXPReplay")]
+    pub tmen_xp_replay: Option<serde_json::Value>,
+    pub veh_type_lock_time: u64,
+    pub win_alone_against_vehicle_count: u32,
+    /// Crew XP per tankman: tankman id and XP
+    pub xp_by_tmen: Vec<(u64, u32)>,
+    pub xp_replay: Option<serde_json::Value>,
+}
+
+impl PersonalVehicleResult {
+    /// Returns the decoded `typeCompDescr`
+    pub fn vehicle_type(&self) -> VehicleTypeDescriptor {
+        self.stats.vehicle_type()
+    }
+}
+
+/// Implements `ValueReplays` for the results with the `*_replay` fields
+macro_rules! impl_value_replays {
+    ($($results:ty),*) => {$(
+        impl ValueReplays for $results {
+            fn value_replay(&self, kind: ValueReplayKind) -> Option<&serde_json::Value> {
+                match kind {
+                    ValueReplayKind::Credits => &self.credits_replay,
+                    ValueReplayKind::Xp => &self.xp_replay,
+                    ValueReplayKind::FreeXp => &self.free_xp_replay,
+                    ValueReplayKind::TmenXp => &self.tmen_xp_replay,
+                    ValueReplayKind::Crystal => &self.crystal_replay,
+                    ValueReplayKind::Gold => &self.gold_replay,
+                    ValueReplayKind::Bpcoin => &self.bpcoin_replay,
+                    ValueReplayKind::EventCoin => &self.event_coin_replay,
+                    ValueReplayKind::FlXp => &self.fl_xp_replay,
+                }
+                .as_ref()
+            }
+        }
+    )*};
+}
+
+impl_value_replays!(PersonalAvatarResults, PersonalVehicleResult);
+
+/// Key of the personal results `details`, the `(vehicleId, typeCompDescr)` string
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(try_from = "String", into = "String")]
+pub struct InteractionKey {
+    /// Vehicle id of the target, the key of `BattleInfo.vehicles`
+    pub vehicle_id: u32,
+    /// `typeCompDescr` of the target, `0` when the client doesn't record it
+    pub type_comp_descr: u32,
+}
+
+impl str::FromStr for InteractionKey {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let inner = s
+            .trim()
+            .strip_prefix('(')
+            .and_then(|s| s.strip_suffix(')'))
+            .ok_or("Interaction key is not a tuple")?;
+        let mut parts = inner.split(',').map(|p| p.trim().parse::<u32>());
+        match (parts.next(), parts.next(), parts.next()) {
+            (Some(Ok(vehicle_id)), Some(Ok(type_comp_descr)), None) => Ok(InteractionKey {
+                vehicle_id,
+                type_comp_descr,
+            }),
+            _ => Err("Invalid interaction key"),
+        }
+    }
+}
+
+impl TryFrom<String> for InteractionKey {
+    type Error = &'static str;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<InteractionKey> for String {
+    fn from(key: InteractionKey) -> Self {
+        key.to_string()
+    }
+}
+
+impl std::fmt::Display for InteractionKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "({}, {})", self.vehicle_id, self.type_comp_descr)
+    }
+}
+
+/// What the player who recorded the replay did to the target vehicle
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct InteractionDetails {
+    pub spotted: u8,
+    pub damage_dealt: u32,
+    pub damage_received: u32,
+    pub damage_blocked_by_armor: u32,
+    pub damage_assisted_radio: u32,
+    pub damage_assisted_track: u32,
+    pub damage_assisted_stun: u32,
+    pub damage_assisted_smoke: u32,
+    pub damage_assisted_inspire: u32,
+    pub direct_hits: u32,
+    pub direct_enemy_hits: u32,
+    pub explosion_hits: u32,
+    pub piercings: u16,
+    pub piercing_enemy_hits: u16,
+    pub no_damage_direct_hits_received: u16,
+    #[serde(rename = "rickochetsReceived")]
+    pub ricochets_received: u16,
+    /// Packed bit mask of the damaged modules and the injured crew
+    pub crits: u32,
+    /// Number of the fires started
+    pub fire: u16,
+    pub stun_num: u32,
+    pub stun_duration: f32,
+    pub target_kills: u8,
+    pub death_reason: crate::dictionaries::DeathReason,
+    /// The keys the model doesn't know, e.g. added by a game update
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
+}
+
+impl InteractionDetails {
+    /// Returns the decoded `crits`
+    pub fn critical_hits(&self) -> crate::crits::CriticalHits {
+        crate::crits::CriticalHits::from_packed(self.crits)
+    }
+}
+
+/// The personal vehicle results keyed by the vehicle `typeCompDescr`
+pub type PersonalVehicleResults = HashMap<VehicleTypeDescriptor, PersonalVehicleResult>;
+
+/// The `personal` object of the results. It's (de)serialized as a single JSON object: the
+/// `avatar` key, the `typeCompDescr` keys of the vehicles and the other keys.
+#[derive(Debug)]
 pub struct PersonalBattleResults {
     pub avatar: PersonalAvatarResults,
+    /// The vehicles the player used in the battle
+    pub vehicles: PersonalVehicleResults,
+    /// The keys which are neither `avatar` nor a vehicle descriptor, e.g. added by a game
+    /// update
+    pub extra: HashMap<String, serde_json::Value>,
+}
+
+impl Serialize for PersonalBattleResults {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeMap;
+
+        let mut map = serializer.serialize_map(Some(1 + self.vehicles.len() + self.extra.len()))?;
+        map.serialize_entry("avatar", &self.avatar)?;
+        for (vehicle_type, vehicle) in &self.vehicles {
+            map.serialize_entry(&vehicle_type.to_string(), vehicle)?;
+        }
+        for (key, value) in &self.extra {
+            map.serialize_entry(key, value)?;
+        }
+        map.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for PersonalBattleResults {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct PersonalVisitor;
+
+        impl<'de> serde::de::Visitor<'de> for PersonalVisitor {
+            type Value = PersonalBattleResults;
+
+            fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                formatter.write_str("the personal results object")
+            }
+
+            fn visit_map<A: serde::de::MapAccess<'de>>(
+                self,
+                mut map: A,
+            ) -> Result<Self::Value, A::Error> {
+                let mut avatar = None;
+                let mut vehicles = HashMap::new();
+                let mut extra = HashMap::new();
+                while let Some(key) = map.next_key::<String>()? {
+                    if key == "avatar" {
+                        avatar = Some(map.next_value()?);
+                    } else if let Ok(vehicle_type) = key.parse::<VehicleTypeDescriptor>() {
+                        vehicles.insert(vehicle_type, map.next_value()?);
+                    } else {
+                        extra.insert(key, map.next_value()?);
+                    }
+                }
+                Ok(PersonalBattleResults {
+                    avatar: avatar.ok_or_else(|| serde::de::Error::missing_field("avatar"))?,
+                    vehicles,
+                    extra,
+                })
+            }
+        }
+
+        deserializer.deserialize_map(PersonalVisitor)
+    }
+}
+
+impl PersonalBattleResults {
+    pub fn vehicle(&self, vehicle_type: &VehicleTypeDescriptor) -> Option<&PersonalVehicleResult> {
+        self.vehicles.get(vehicle_type)
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -368,16 +1358,18 @@ pub struct PlayerInfo {
     pub real_name: String,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-#[serde(rename_all = "camelCase")]
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase", default)]
 pub struct CommonBattleInfo {
     pub division: Option<u32>,
     pub finish_reason: crate::dictionaries::FinishReason,
-    pub gui_type: u32,
+    pub gui_type: crate::dictionaries::GuiType,
     pub common_num_defended: u32,
     pub common_num_captured: u32,
     pub common_num_started: u32,
-    pub arena_create_time: u128,
+    /// Unix timestamp of the arena creation. It's `u64` as the `u128` values can't be
+    /// deserialized next to the flattened `extra` map.
+    pub arena_create_time: u64,
     pub common_num_destroyed: u32,
     pub duration: u32,
     pub team_health: HashMap<String, u32>,
@@ -385,10 +1377,22 @@ pub struct CommonBattleInfo {
     pub arena_type_id: u32,
     pub gas_attack_winner_team: i32,
     pub winner_team: u16,
+    /// Vehicle lock mode of the clan battles. It's kept raw: only `0` (no lock) is seen in
+    /// the known replays, the ids of the other modes are not confirmed.
     pub veh_lock_mode: u16,
-    pub bonus_type: u16,
+    pub bonus_type: crate::dictionaries::BonusType,
     pub bots: serde_json::Value,
     pub account_comp_descr: Option<serde_json::Value>,
+    /// The keys the model doesn't know, e.g. added by a game update
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
+}
+
+impl CommonBattleInfo {
+    /// Returns the decoded `arena_type_id`
+    pub fn arena_type(&self) -> crate::arena::ArenaType {
+        crate::arena::ArenaType::from_packed(self.arena_type_id)
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -429,6 +1433,13 @@ pub struct PlayerResults {
     pub name: String,
 }
 
+impl PlayerResults {
+    /// Parses the `vehicle_type`
+    pub fn vehicle_tag(&self) -> Option<VehicleTag> {
+        self.vehicle_type.parse().ok()
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct PlayerFrags {
@@ -439,7 +1450,181 @@ pub type PlayersResults = HashMap<String, PlayerResults>;
 
 pub type Frags = HashMap<String, PlayerFrags>;
 
-pub type BattleResults = (GeneralBattleResults, PlayersResults, Frags);
+/// The battle results data chunk. It's stored as the three-element JSON array
+/// `[general, players, frags]`.
+#[derive(Debug)]
+pub struct BattleResults {
+    pub general: GeneralBattleResults,
+    /// Player results keyed by the vehicle id
+    pub players: PlayersResults,
+    /// Frags keyed by the vehicle id
+    pub frags: Frags,
+}
+
+impl BattleResults {
+    pub fn common(&self) -> &CommonBattleInfo {
+        &self.general.common
+    }
+
+    /// Results of the player who recorded the replay
+    pub fn personal(&self) -> &PersonalBattleResults {
+        &self.general.personal
+    }
+
+    pub fn winner_team(&self) -> u16 {
+        self.general.common.winner_team
+    }
+
+    /// Returns the results of the vehicles the player used by the vehicle id
+    pub fn vehicle(&self, vehicle_id: u32) -> Option<&[VehicleResult]> {
+        self.general
+            .vehicles
+            .get(&vehicle_id.to_string())
+            .map(|v| v.as_slice())
+    }
+
+    pub fn player(&self, vehicle_id: u32) -> Option<&PlayerResults> {
+        self.players.get(&vehicle_id.to_string())
+    }
+
+    pub fn frags_of(&self, vehicle_id: u32) -> Option<u8> {
+        self.frags.get(&vehicle_id.to_string()).map(|f| f.frags)
+    }
+}
+
+impl Serialize for BattleResults {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        (&self.general, &self.players, &self.frags).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for BattleResults {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let (general, players, frags) = Deserialize::deserialize(deserializer)?;
+        Ok(BattleResults {
+            general,
+            players,
+            frags,
+        })
+    }
+}
+
+fn parse_client_version(from_xml: Option<&str>, from_exe: Option<&str>) -> Option<ClientVersion> {
+    let from_xml = from_xml.and_then(|v| v.parse::<ClientVersion>().ok());
+    let from_exe = from_exe.and_then(|v| v.parse::<ClientVersion>().ok());
+    match (from_exe, from_xml) {
+        (Some(exe), Some(xml)) if exe.without_revision() == xml.without_revision() => Some(xml),
+        (Some(exe), _) => Some(exe),
+        (None, xml) => xml,
+    }
+}
+
+/// Checks the leniently parsed results have neither missing nor unknown fields
+fn check_results_schema(results: &BattleResults, raw_results: &[u8]) -> Result<(), ReplayError> {
+    let raw: serde_json::Value =
+        serde_json::from_slice(raw_results).map_err(|e| ReplayError::Json {
+            chunk: 1,
+            path: ".".to_string(),
+            source: e,
+        })?;
+    let general = &results.general;
+    check_schema(
+        &general.common,
+        &general.common.extra,
+        &raw[0]["common"],
+        "[0].common",
+    )?;
+    check_schema(
+        &general.personal.avatar,
+        &general.personal.avatar.extra,
+        &raw[0]["personal"]["avatar"],
+        "[0].personal.avatar",
+    )?;
+    if !general.personal.extra.is_empty() {
+        let mut unknown: Vec<String> = general.personal.extra.keys().cloned().collect();
+        unknown.sort();
+        return Err(ReplayError::Schema {
+            chunk: 1,
+            path: "[0].personal".to_string(),
+            missing: vec![],
+            unknown,
+        });
+    }
+    let mut vehicle_types: Vec<_> = general.personal.vehicles.keys().collect();
+    vehicle_types.sort();
+    for vehicle_type in vehicle_types {
+        let vehicle = &general.personal.vehicles[vehicle_type];
+        check_schema(
+            vehicle,
+            &vehicle.stats.extra,
+            &raw[0]["personal"][vehicle_type.to_string()],
+            &format!("[0].personal.{}", vehicle_type),
+        )?;
+        for (key, details) in &vehicle.details {
+            check_schema(
+                details,
+                &details.extra,
+                &raw[0]["personal"][vehicle_type.to_string()]["details"][key.to_string()],
+                &format!("[0].personal.{}.details.{}", vehicle_type, key),
+            )?;
+        }
+    }
+    let mut vehicle_ids: Vec<_> = general.vehicles.keys().collect();
+    vehicle_ids.sort();
+    for vehicle_id in vehicle_ids {
+        for (i, vehicle) in general.vehicles[vehicle_id].iter().enumerate() {
+            check_schema(
+                vehicle,
+                &vehicle.extra,
+                &raw[0]["vehicles"][vehicle_id][i],
+                &format!("[0].vehicles.{}[{}]", vehicle_id, i),
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Compares the keys of the raw JSON object with the fields of the model. The fields
+/// are taken from the serialized model without the `extra` keys.
+fn check_schema<T: Serialize>(
+    model: &T,
+    extra: &HashMap<String, serde_json::Value>,
+    raw: &serde_json::Value,
+    path: &str,
+) -> Result<(), ReplayError> {
+    let json_error = |source| ReplayError::Json {
+        chunk: 1,
+        path: path.to_string(),
+        source,
+    };
+    let fields = match serde_json::to_value(model).map_err(json_error)? {
+        serde_json::Value::Object(fields) => fields,
+        _ => {
+            return Err(json_error(serde::ser::Error::custom(
+                "The model is not serialized as a JSON object",
+            )))
+        }
+    };
+    let raw = raw.as_object();
+    let mut missing: Vec<String> = fields
+        .keys()
+        .filter(|k| !extra.contains_key(*k))
+        .filter(|k| raw.is_none_or(|raw| !raw.contains_key(*k)))
+        .cloned()
+        .collect();
+    let mut unknown: Vec<String> = extra.keys().cloned().collect();
+    if missing.is_empty() && unknown.is_empty() {
+        return Ok(());
+    }
+    missing.sort();
+    unknown.sort();
+    Err(ReplayError::Schema {
+        chunk: 1,
+        path: path.to_string(),
+        missing,
+        unknown,
+    })
+}
 
 mod wot_date_format {
     use chrono::{DateTime, TimeZone, Utc};
@@ -480,19 +1665,569 @@ mod wot_date_format {
 }
 
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     use super::*;
     use std::fs;
 
+    /// Reads the replay files of `test_data`. They are stored in git-lfs, so the tests using
+    /// them are ignored by default and fail if the LFS content is not fetched.
+    pub(crate) fn test_data_replays() -> Vec<(std::path::PathBuf, Vec<u8>)> {
+        let mut replays = vec![];
+        for entry in fs::read_dir("test_data").unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension() != Some(std::ffi::OsStr::new("wotreplay")) {
+                continue;
+            }
+            let content = fs::read(&path).unwrap();
+            assert!(
+                !content.starts_with(b"version https://git-lfs"),
+                "{:?}: LFS content not fetched, run `git lfs pull`",
+                path
+            );
+            replays.push((path, content));
+        }
+        assert!(!replays.is_empty(), "No replay files in test_data");
+        replays
+    }
+
+    pub(crate) fn build_raw_replay() -> RawReplay {
+        let battle_info = fs::read("test_data/batlle_info.json").unwrap();
+        let battle_results = fs::read("test_data/battle_results.json").unwrap();
+        let stream = crate::packets::tests::frame(0x0a, 1.0, &[0u8; 49]);
+        RawReplay {
+            magic: 288633362,
+            data_chunks: 2,
+            data: vec![DataChunk::new(battle_info), DataChunk::new(battle_results)],
+            replay: crate::battle_stream::tests::pack(&stream),
+            data_only: false,
+        }
+    }
+
+    #[test]
+    fn test_raw_replay_round_trip() {
+        let mut buf = vec![];
+        build_raw_replay().write(&mut buf).unwrap();
+        let raw_replay = RawReplay::read(&mut &buf[..]).unwrap();
+        assert_eq!(raw_replay.data_chunks, 2);
+        assert!(Replay::try_from(&raw_replay).unwrap().results.is_some());
+        assert_eq!(raw_replay.battle_stream().unwrap().len(), 61);
+
+        let mut written = vec![];
+        raw_replay.write(&mut written).unwrap();
+        assert_eq!(written, buf);
+    }
+
+    #[test]
+    fn test_synthesized_replay_round_trip() {
+        // Built by hand to not depend on the writer
+        let chunks = [
+            fs::read("test_data/batlle_info.json").unwrap(),
+            fs::read("test_data/battle_results.json").unwrap(),
+        ];
+        let mut original = vec![0x12, 0x32, 0x34, 0x11, 2, 0, 0, 0];
+        for chunk in &chunks {
+            original.extend_from_slice(&(chunk.len() as u32).to_le_bytes());
+            original.extend_from_slice(chunk);
+        }
+        let stream = crate::packets::tests::frame(0x0a, 1.0, &[0u8; 49]);
+        original.extend(crate::battle_stream::tests::pack(&stream));
+
+        let raw_replay = RawReplay::read(&mut &original[..]).unwrap();
+        assert_eq!(raw_replay.data[1].payload, chunks[1]);
+        let mut written = vec![];
+        raw_replay.write(&mut written).unwrap();
+        assert!(written == original, "The written replay differs");
+    }
+
+    #[test]
+    #[ignore = "needs the git-lfs content of test_data/*.wotreplay"]
+    fn test_replay_files_round_trip() {
+        for (path, original) in test_data_replays() {
+            let raw_replay = RawReplay::read(&mut &original[..]).unwrap();
+            let mut written = vec![];
+            raw_replay.write(&mut written).unwrap();
+            assert!(written == original, "{:?} differs", path);
+        }
+    }
+
+    #[test]
+    fn test_write_errors() {
+        let mut buf = vec![];
+        build_raw_replay().write(&mut buf).unwrap();
+
+        let raw_replay = RawReplay::read_data_only(&mut &buf[..]).unwrap();
+        assert!(raw_replay.data_only);
+        assert!(matches!(
+            raw_replay.write(&mut vec![]),
+            Err(ReplayError::Unwritable(_))
+        ));
+
+        let mut raw_replay = build_raw_replay();
+        raw_replay.data[0].payload.push(b' ');
+        assert!(matches!(
+            raw_replay.write(&mut vec![]),
+            Err(ReplayError::Unwritable(_))
+        ));
+        let payload = raw_replay.data[0].payload.clone();
+        raw_replay.data[0].set_payload(payload);
+        raw_replay.write(&mut vec![]).unwrap();
+
+        raw_replay.data.pop();
+        assert!(matches!(
+            raw_replay.write(&mut vec![]),
+            Err(ReplayError::Unwritable(_))
+        ));
+    }
+
+    #[test]
+    fn test_replay_errors() {
+        let mut buf = vec![];
+        build_raw_replay().write(&mut buf).unwrap();
+
+        match RawReplay::read(&mut &b"\x12\x32\x34\x12"[..]) {
+            Err(ReplayError::BadMagic(magic)) => assert_eq!(magic, [0x12, 0x32, 0x34, 0x12]),
+            r => panic!("Unexpected result: {:?}", r.map(|_| ())),
+        }
+        match RawReplay::read(&mut &buf[..100]) {
+            Err(ReplayError::TruncatedChunk { index, .. }) => assert_eq!(index, 0),
+            r => panic!("Unexpected result: {:?}", r.map(|_| ())),
+        }
+
+        let mut raw_replay = build_raw_replay();
+        raw_replay.data[1] = DataChunk::new(br#"[{"arenaUniqueID": "x"}, {}, {}]"#.to_vec());
+        match Replay::try_from(&raw_replay) {
+            Err(ReplayError::Json { chunk, path, .. }) => {
+                assert_eq!(chunk, 1);
+                assert_eq!(path, "[0].arenaUniqueID");
+            }
+            r => panic!("Unexpected result: {:?}", r.map(|_| ())),
+        }
+        raw_replay.data.pop();
+        match Replay::try_from(&raw_replay) {
+            Err(ReplayError::MissingResults { data_chunks }) => assert_eq!(data_chunks, 2),
+            r => panic!("Unexpected result: {:?}", r.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn test_parse_modes() {
+        let raw_replay = build_raw_replay();
+        let replay = Replay::parse(&raw_replay, ParseMode::Strict).unwrap();
+        assert!(replay.results.unwrap().common().extra.is_empty());
+
+        let mut results: serde_json::Value =
+            serde_json::from_slice(&raw_replay.data[1].payload).unwrap();
+        let common = results[0]["common"].as_object_mut().unwrap();
+        common.remove("duration");
+        common.remove("winnerTeam");
+        common.insert("newField".to_string(), serde_json::json!([1, 2]));
+        let mut raw_replay = build_raw_replay();
+        raw_replay.data[1] = DataChunk::new(serde_json::to_vec(&results).unwrap());
+
+        let vehicle = results[0]["vehicles"]["10335956"][0]
+            .as_object_mut()
+            .unwrap();
+        vehicle.remove("stunNum");
+        vehicle.remove("damageAssistedStun");
+        let avatar = results[0]["personal"]["avatar"].as_object_mut().unwrap();
+        avatar.remove("hasBattlePass");
+        raw_replay.data[1] = DataChunk::new(serde_json::to_vec(&results).unwrap());
+
+        let replay = Replay::try_from(&raw_replay).unwrap();
+        let results = replay.results.unwrap();
+        let vehicle = &results.vehicle(10335956).unwrap()[0];
+        assert_eq!(vehicle.stun_num, None);
+        assert_eq!(vehicle.damage_assisted_stun, None);
+        assert_eq!(vehicle.stunned, Some(0));
+        assert_eq!(results.personal().avatar.has_battle_pass, None);
+        let common = results.general.common;
+        assert_eq!(common.duration, 0);
+        assert_eq!(common.extra["newField"], serde_json::json!([1, 2]));
+        match Replay::parse(&raw_replay, ParseMode::Strict) {
+            Err(ReplayError::Schema {
+                chunk,
+                path,
+                missing,
+                unknown,
+            }) => {
+                assert_eq!(chunk, 1);
+                assert_eq!(path, "[0].common");
+                assert_eq!(missing, vec!["duration", "winnerTeam"]);
+                assert_eq!(unknown, vec!["newField"]);
+            }
+            r => panic!("Unexpected result: {:?}", r.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn test_players() {
+        let replay = Replay::try_from(&build_raw_replay()).unwrap();
+        let players = replay.players();
+        assert_eq!(players.len(), 30);
+        assert!(players.windows(2).all(|w| w[0].team <= w[1].team));
+        let recording: Vec<_> = players.iter().filter(|p| p.is_recording_player).collect();
+        assert_eq!(recording.len(), 1);
+        let player = recording[0];
+        assert_eq!(player.vehicle_id, 10335956);
+        assert_eq!(player.account_id, Some(118809929));
+        assert_eq!(player.name, "Near_Hrew");
+        assert_eq!(player.fake_name, "fint_Zlat87");
+        assert_eq!(player.clan, Some("RCFK"));
+        assert_eq!(player.team, 2);
+        assert_eq!(
+            player.vehicle,
+            Some(VehicleTag::new(
+                crate::vehicles::Nation::Ussr,
+                "R158_LT_432"
+            ))
+        );
+        assert_eq!(player.vehicle_type.map(|v| v.packed()), Some(45569));
+        assert_eq!(player.frags, Some(1));
+        assert!(player.avatar.is_some());
+        assert_eq!(player.player_info.unwrap().real_name, "Near_Hrew");
+        assert_eq!(player.vehicle_results.unwrap().len(), 1);
+
+        let replay = Replay {
+            results: None,
+            ..replay
+        };
+        let players = replay.players();
+        let player = players.iter().find(|p| p.is_recording_player).unwrap();
+        assert_eq!(player.account_id, None);
+        assert_eq!(player.frags, None);
+        assert!(player.is_alive);
+    }
+
+    #[test]
+    fn test_personal_vehicle_results() {
+        let replay = Replay::try_from(&build_raw_replay()).unwrap();
+        let results = replay.results.unwrap();
+        let personal = results.personal();
+        assert_eq!(personal.vehicles.len(), 1);
+        let descriptor = VehicleTypeDescriptor::from_packed(45569);
+        let vehicle = personal.vehicle(&descriptor).unwrap();
+        assert_eq!(vehicle.vehicle_type(), descriptor);
+        assert_eq!(vehicle.stats.damage_dealt, 1033);
+        assert_eq!(
+            vehicle.stats.achievements(),
+            vec![crate::dictionaries::Achievement::Evileye]
+        );
+        assert_eq!(vehicle.marks_on_gun, 1);
+        assert_eq!(vehicle.moving_avg_damage, 1913);
+        assert_eq!(vehicle.mark_of_mastery, 4);
+        assert_eq!(vehicle.prem_mask, 2);
+        assert_eq!(vehicle.auto_load_cost, vec![32000, 0]);
+        assert_eq!(vehicle.piggy_bank, 19647);
+        assert_eq!(vehicle.applied_premium_credits_factor100, 150);
+        assert_eq!(vehicle.booster_t_men_xp_factor100, 300);
+        assert_eq!(vehicle.xp_by_tmen[0], (152, 17968));
+        assert!(vehicle.stats.extra.is_empty());
+
+        let value = serde_json::to_value(personal).unwrap();
+        assert_eq!(value["45569"]["marksOnGun"], 1);
+        assert_eq!(value["45569"]["damageDealt"], 1033);
+    }
+
+    #[test]
+    fn test_personal_extra_keys() {
+        let mut raw_replay = build_raw_replay();
+        let mut results: serde_json::Value =
+            serde_json::from_slice(&raw_replay.data[1].payload).unwrap();
+        let personal = results[0]["personal"].as_object_mut().unwrap();
+        personal.insert("newKey".to_string(), serde_json::json!({"points": 5}));
+        raw_replay.data[1] = DataChunk::new(serde_json::to_vec(&results).unwrap());
+
+        let replay = Replay::try_from(&raw_replay).unwrap();
+        let results = replay.results.unwrap();
+        let personal = results.personal();
+        assert_eq!(personal.vehicles.len(), 1);
+        assert_eq!(personal.extra["newKey"], serde_json::json!({"points": 5}));
+        let value = serde_json::to_value(personal).unwrap();
+        assert_eq!(value["newKey"]["points"], 5);
+        assert!(value["avatar"].is_object());
+
+        match Replay::parse(&raw_replay, ParseMode::Strict) {
+            Err(ReplayError::Schema { path, unknown, .. }) => {
+                assert_eq!(path, "[0].personal");
+                assert_eq!(unknown, vec!["newKey"]);
+            }
+            r => panic!("Unexpected result: {:?}", r.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn test_economy_steps() {
+        let replay = Replay::try_from(&build_raw_replay()).unwrap();
+        let mut results = replay.results.unwrap();
+        let decoder = crate::economy::ValueReplayDecoder::new(vec![]);
+        let avatar = &mut results.general.personal.avatar;
+        assert_eq!(
+            avatar.economy_steps(ValueReplayKind::Credits, &decoder),
+            Ok(vec![])
+        );
+        avatar.xp_replay = Some(serde_json::json!([["set", "originalXP", 1426]]));
+        let steps = avatar.economy_steps(ValueReplayKind::Xp, &decoder).unwrap();
+        assert_eq!(steps[0].name, "originalXP");
+        assert_eq!(steps[0].value, 1426);
+    }
+
+    #[test]
+    fn test_economics() {
+        let replay = Replay::try_from(&build_raw_replay()).unwrap();
+        let economics = replay.economics().unwrap();
+        assert!(economics.has_premium);
+        assert_eq!(economics.credits.value, 196466);
+        assert_eq!(economics.credits.without_premium, 130977);
+        assert_eq!(economics.credits.booster, 65489);
+        assert_eq!(economics.xp.without_premium, 4421);
+        assert_eq!(economics.xp.event, 2139);
+        assert_eq!(economics.free_xp.without_premium, 71);
+        assert_eq!(economics.ammo, 32000);
+        assert_eq!(economics.net_credits(), 164466);
+        assert_eq!(economics.xp.value, 5562);
+        assert_eq!(economics.tmen_xp.value, 11979);
+        assert_eq!(economics.piggy_bank, 19647);
+
+        let replay = Replay {
+            results: None,
+            ..replay
+        };
+        assert!(replay.economics().is_none());
+    }
+
+    #[test]
+    fn test_interactions() {
+        let key: InteractionKey = "(10335955, 0)".parse().unwrap();
+        assert_eq!(key.vehicle_id, 10335955);
+        assert_eq!(key.to_string(), "(10335955, 0)");
+        assert!("10335955".parse::<InteractionKey>().is_err());
+        assert!("(1, 2, 3)".parse::<InteractionKey>().is_err());
+
+        let replay = Replay::try_from(&build_raw_replay()).unwrap();
+        let interactions = replay.interactions();
+        assert_eq!(interactions.len(), 11);
+        let killed: Vec<_> = interactions
+            .iter()
+            .filter(|i| i.details.target_kills > 0)
+            .collect();
+        assert_eq!(killed.len(), 1);
+        assert_eq!(killed[0].target_id, 10335965);
+        assert_eq!(
+            killed[0].target.unwrap().vehicle_type,
+            "italy:It15_Rinoceronte"
+        );
+        assert_eq!(killed[0].details.damage_dealt, 493);
+        assert_eq!(killed[0].details.piercings, 3);
+        assert_eq!(
+            killed[0].details.critical_hits().damaged_modules,
+            vec![crate::crits::VehicleModule::Track]
+        );
+        let damage: u32 = interactions.iter().map(|i| i.details.damage_dealt).sum();
+        assert_eq!(damage, 1033);
+    }
+
+    #[test]
+    fn test_client_version() {
+        let raw_replay = build_raw_replay();
+        let expected = ClientVersion {
+            revision: Some(774),
+            ..ClientVersion::new(1, 12, 0, 0)
+        };
+        assert_eq!(raw_replay.client_version(), Some(expected));
+        let replay = Replay::try_from(&raw_replay).unwrap();
+        assert_eq!(replay.battle_info.client_version(), Some(expected));
+    }
+
+    #[test]
+    fn test_vehicle_descriptors() {
+        let replay = Replay::try_from(&build_raw_replay()).unwrap();
+        let tag = replay.battle_info.player_vehicle_tag().unwrap();
+        assert_eq!(
+            tag,
+            VehicleTag::new(crate::vehicles::Nation::Ussr, "R158_LT_432")
+        );
+        let descriptors = replay.vehicle_descriptors();
+        for (tag, descriptor) in &descriptors {
+            assert_eq!(descriptor.nation(), Some(tag.nation));
+        }
+        assert_eq!(descriptors[&tag], VehicleTypeDescriptor::from_packed(45569));
+    }
+
+    #[test]
+    fn test_bonus_and_gui_types() {
+        use crate::dictionaries::{BonusType, GuiType};
+        let replay = Replay::try_from(&build_raw_replay()).unwrap();
+        assert_eq!(replay.battle_info.battle_type, BonusType::Regular);
+        let results = replay.results.unwrap();
+        let common = results.common();
+        assert_eq!(common.bonus_type, BonusType::Regular);
+        assert_eq!(common.gui_type, GuiType::Random);
+
+        assert_eq!(
+            serde_json::from_str::<BonusType>("27").unwrap(),
+            BonusType::EpicBattle
+        );
+        assert_eq!(
+            serde_json::from_str::<BonusType>("1000").unwrap(),
+            BonusType::Unknown(1000)
+        );
+        assert_eq!(
+            serde_json::to_string(&BonusType::Unknown(1000)).unwrap(),
+            "1000"
+        );
+        assert_eq!(serde_json::to_string(&GuiType::Random).unwrap(), "1");
+    }
+
+    #[test]
+    fn test_recover() {
+        let raw_replay = build_raw_replay();
+        let recovered = raw_replay.recover().unwrap();
+        assert_eq!(recovered.completeness, Completeness::Complete);
+        assert_eq!(recovered.last_clock, Some(1.0));
+        assert!(recovered.results_error.is_none());
+
+        let mut raw_replay = build_raw_replay();
+        raw_replay.data.pop();
+        raw_replay.data_chunks = 1;
+        let mut stream = crate::packets::tests::frame(0x0a, 1.0, &[0u8; 49]);
+        stream.extend(crate::packets::tests::frame(0x0a, 2.5, &[0u8; 49]));
+        let packed = crate::battle_stream::tests::pack(&stream);
+        raw_replay.replay = packed[..packed.len() - 8].to_vec();
+        let recovered = raw_replay.recover().unwrap();
+        assert_eq!(recovered.completeness, Completeness::BattleInfoOnly);
+        assert!(recovered.replay.results.is_none());
+
+        // The frames have the same size, the stream is cut in the middle of the battle
+        let frame_size = crate::packets::tests::frame(0x0a, 0.0, &[0u8; 49]).len();
+        let stream: Vec<u8> = (0..2000u32)
+            .flat_map(|i| {
+                let payload: Vec<u8> = (0..49u32).map(|j| (i * 31 + j * 7) as u8).collect();
+                crate::packets::tests::frame(0x0a, i as f32 * 0.5, &payload)
+            })
+            .collect();
+        let packed = crate::battle_stream::tests::pack(&stream);
+        let mut raw_replay = build_raw_replay();
+        raw_replay.replay = packed[..packed.len() / 2].to_vec();
+        let recovered = raw_replay.recover().unwrap();
+        assert_eq!(recovered.completeness, Completeness::TruncatedBattleStream);
+        let complete_frames = recovered.battle_stream.len() / frame_size;
+        assert!(complete_frames > 0 && complete_frames < 2000);
+        assert_eq!(
+            recovered.last_clock,
+            Some((complete_frames - 1) as f32 * 0.5)
+        );
+
+        let mut raw_replay = build_raw_replay();
+        raw_replay.data[1] = DataChunk::new(b"[{}]".to_vec());
+        let recovered = raw_replay.recover().unwrap();
+        assert_eq!(recovered.completeness, Completeness::BattleInfoOnly);
+        assert!(matches!(
+            recovered.results_error,
+            Some(ReplayError::Json { chunk: 1, .. })
+        ));
+        assert_eq!(recovered.last_clock, Some(1.0));
+
+        let mut buf = vec![];
+        build_raw_replay().write(&mut buf).unwrap();
+        let raw_replay = RawReplay::read_data_only(&mut &buf[..]).unwrap();
+        let recovered = raw_replay.recover().unwrap();
+        assert_eq!(recovered.completeness, Completeness::BattleStreamNotRead);
+        assert_eq!(recovered.last_clock, None);
+    }
+
+    #[test]
+    fn test_reader_limits() {
+        let mut buf = vec![];
+        build_raw_replay().write(&mut buf).unwrap();
+        let assert_limit =
+            |limits: ReaderLimits, expected: ReaderLimit| match RawReplay::read_with_limits(
+                &mut &buf[..],
+                &limits,
+            ) {
+                Err(ReplayError::LimitExceeded { limit, .. }) => assert_eq!(limit, expected),
+                r => panic!("Unexpected result: {:?}", r.map(|_| ())),
+            };
+        assert_limit(
+            ReaderLimits {
+                max_chunk_size: 1024,
+                ..ReaderLimits::default()
+            },
+            ReaderLimit::ChunkSize,
+        );
+        assert_limit(
+            ReaderLimits {
+                max_chunk_count: 1,
+                ..ReaderLimits::default()
+            },
+            ReaderLimit::ChunkCount,
+        );
+        assert_limit(
+            ReaderLimits {
+                max_total_size: 100_000,
+                ..ReaderLimits::default()
+            },
+            ReaderLimit::TotalSize,
+        );
+        assert_limit(
+            ReaderLimits {
+                max_total_size: buf.len() as u64 - 1,
+                ..ReaderLimits::default()
+            },
+            ReaderLimit::TotalSize,
+        );
+        let limits = ReaderLimits {
+            max_total_size: buf.len() as u64,
+            ..ReaderLimits::default()
+        };
+        assert!(RawReplay::read_with_limits(&mut &buf[..], &limits).is_ok());
+
+        // The hostile chunk length doesn't lead to the huge allocation
+        let hostile = [0x12, 0x32, 0x34, 0x11, 1, 0, 0, 0, 0xff, 0xff, 0xff, 0xff];
+        match RawReplay::read(&mut &hostile[..]) {
+            Err(ReplayError::LimitExceeded { limit, value, .. }) => {
+                assert_eq!(limit, ReaderLimit::ChunkSize);
+                assert_eq!(value, u32::MAX as u64);
+            }
+            r => panic!("Unexpected result: {:?}", r.map(|_| ())),
+        }
+        // The length within the chunk size limit is not allocated before the data is read
+        let hostile = [0x12, 0x32, 0x34, 0x11, 1, 0, 0, 0, 0, 0, 0, 0x04];
+        match RawReplay::read(&mut &hostile[..]) {
+            Err(ReplayError::TruncatedChunk { index, .. }) => assert_eq!(index, 0),
+            r => panic!("Unexpected result: {:?}", r.map(|_| ())),
+        }
+        // The total size is checked before the chunk is read
+        let limits = ReaderLimits {
+            max_total_size: 100,
+            ..ReaderLimits::default()
+        };
+        let hostile = [0x12, 0x32, 0x34, 0x11, 1, 0, 0, 0, 89, 0, 0, 0];
+        match RawReplay::read_with_limits(&mut &hostile[..], &limits) {
+            Err(ReplayError::LimitExceeded { limit, value, .. }) => {
+                assert_eq!(limit, ReaderLimit::TotalSize);
+                assert_eq!(value, 101);
+            }
+            r => panic!("Unexpected result: {:?}", r.map(|_| ())),
+        }
+    }
+
     #[test]
     fn test_battle_results_deserializing() {
         let json = fs::read_to_string("test_data/battle_results.json").unwrap();
-        let _result: BattleResults = match serde_json::from_str(&json) {
+        let result: BattleResults = match serde_json::from_str(&json) {
             Ok(v) => v,
             Err(e) => {
                 panic!("Invalid json: {}", e);
             }
         };
-        assert!(true);
+        assert_eq!(result.winner_team(), 2);
+        assert_eq!(result.frags_of(10335956), Some(1));
+        assert_eq!(
+            result.player(10335956).unwrap().vehicle_type,
+            "ussr:R158_LT_432"
+        );
+        assert_eq!(result.vehicle(10335956).unwrap()[0].type_comp_descr, 45569);
+        let value = serde_json::to_value(&result).unwrap();
+        assert_eq!(value.as_array().map(|v| v.len()), Some(3));
     }
 }
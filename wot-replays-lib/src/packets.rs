@@ -0,0 +1,387 @@
+use crate::version::ClientVersion;
+use byteorder::{ByteOrder, LittleEndian};
+use log::trace;
+use std::convert::TryFrom;
+use std::fmt;
+
+pub mod chat;
+pub mod entity;
+pub mod events;
+pub mod position;
+mod reader;
+
+pub use chat::{ChatChannel, ChatMessage};
+pub use entity::{EntityMethod, EntityProperty};
+pub use events::{AvatarBattleEvent, BattleEvent, EntityDefinitions, EventDecoder};
+pub use position::{PositionUpdate, Vector3};
+
+/// Size of the packet header: payload length, packet type and game clock
+pub const PACKET_HEADER_SIZE: usize = 12;
+
+/// Known types of the battle stream packets
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PacketType {
+    BasePlayerCreate,
+    EntityProperty,
+    EntityMethod,
+    PositionUpdate,
+    ChatMessage,
+    Unknown(u32),
+}
+
+/// Raw ids of the packet types. They differ between the client versions.
+///
+/// `PacketTypeIds::for_version` knows only the chat message id of the clients before 0.9,
+/// the other ids are the ones of the 1.x clients. Use `Packets::with_ids` with the ids of
+/// the other clients.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PacketTypeIds {
+    pub base_player_create: u32,
+    pub entity_property: u32,
+    pub entity_method: u32,
+    pub position_update: u32,
+    pub chat_message: u32,
+}
+
+impl PacketTypeIds {
+    /// Returns the packet type ids of the client version
+    pub fn for_version(version: &ClientVersion) -> PacketTypeIds {
+        if version.without_revision() < ClientVersion::new(0, 9, 0, 0) {
+            PacketTypeIds {
+                chat_message: 0x1f,
+                ..PacketTypeIds::default()
+            }
+        } else {
+            PacketTypeIds::default()
+        }
+    }
+
+    /// Returns the known type of the raw packet type id
+    pub fn kind(&self, packet_type: u32) -> PacketType {
+        match packet_type {
+            v if v == self.base_player_create => PacketType::BasePlayerCreate,
+            v if v == self.entity_property => PacketType::EntityProperty,
+            v if v == self.entity_method => PacketType::EntityMethod,
+            v if v == self.position_update => PacketType::PositionUpdate,
+            v if v == self.chat_message => PacketType::ChatMessage,
+            v => PacketType::Unknown(v),
+        }
+    }
+}
+
+impl Default for PacketTypeIds {
+    /// The packet type ids of the current clients
+    fn default() -> Self {
+        PacketTypeIds {
+            base_player_create: 0x00,
+            entity_property: 0x07,
+            entity_method: 0x08,
+            position_update: 0x0a,
+            chat_message: 0x23,
+        }
+    }
+}
+
+/// A single framed packet of the decrypted battle stream
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Packet<'a> {
+    /// Length of the payload in bytes
+    pub length: u32,
+    /// Raw type of the packet
+    pub packet_type: u32,
+    /// Known type of the packet for the client version of the stream
+    pub kind: PacketType,
+    /// Game clock (seconds since the arena was created) the packet was recorded at
+    pub clock: f32,
+    /// The packet payload
+    pub payload: &'a [u8],
+}
+
+impl<'a> Packet<'a> {
+    /// Checks the packet has the expected type and its payload is not shorter than
+    /// `min_length` bytes
+    pub(crate) fn expect_type(
+        &self,
+        expected: PacketType,
+        min_length: usize,
+    ) -> Result<(), PacketError> {
+        if self.kind != expected {
+            return Err(PacketError::UnexpectedType {
+                expected,
+                actual: self.packet_type,
+            });
+        }
+        if self.payload.len() < min_length {
+            return Err(PacketError::PayloadTooShort {
+                packet_type: self.packet_type,
+                length: self.length,
+                expected: min_length,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Errors of the battle stream framing and packet decoding
+#[derive(Debug, Clone, PartialEq)]
+pub enum PacketError {
+    /// The stream ends in the middle of a packet header
+    TruncatedHeader { offset: usize, available: usize },
+    /// The stream ends in the middle of a packet payload
+    TruncatedPayload {
+        offset: usize,
+        length: u32,
+        available: usize,
+    },
+    /// The packet can't be decoded as the requested type
+    UnexpectedType { expected: PacketType, actual: u32 },
+    /// The packet payload is shorter than the decoded type requires
+    PayloadTooShort {
+        packet_type: u32,
+        length: u32,
+        expected: usize,
+    },
+}
+
+impl fmt::Display for PacketError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PacketError::TruncatedHeader { offset, available } => write!(
+                f,
+                "Truncated packet header at offset {}: {} of {} bytes available",
+                offset, available, PACKET_HEADER_SIZE
+            ),
+            PacketError::TruncatedPayload {
+                offset,
+                length,
+                available,
+            } => write!(
+                f,
+                "Truncated packet payload at offset {}: {} of {} bytes available",
+                offset, available, length
+            ),
+            PacketError::UnexpectedType { expected, actual } => write!(
+                f,
+                "Unexpected packet type {:#x}, {:?} expected",
+                actual, expected
+            ),
+            PacketError::PayloadTooShort {
+                packet_type,
+                length,
+                expected,
+            } => write!(
+                f,
+                "Payload of the packet type {:#x} is too short: {} bytes, at least {} expected",
+                packet_type, length, expected
+            ),
+        }
+    }
+}
+
+impl std::error::Error for PacketError {}
+
+/// Lazy iterator over the packets of the decrypted battle stream (see
+/// `RawReplay::battle_stream`). The packets are decoded on demand and borrow the payload
+/// from the stream buffer. The iteration stops after the first framing error.
+#[derive(Debug, Clone)]
+pub struct Packets<'a> {
+    data: &'a [u8],
+    offset: usize,
+    failed: bool,
+    ids: PacketTypeIds,
+}
+
+impl<'a> Packets<'a> {
+    /// Creates the iterator over the stream of the current client version
+    pub fn new(data: &'a [u8]) -> Packets<'a> {
+        Self::with_ids(data, PacketTypeIds::default())
+    }
+
+    /// Creates the iterator over the stream recorded by the given client version
+    pub fn with_version(data: &'a [u8], version: &ClientVersion) -> Packets<'a> {
+        Self::with_ids(data, PacketTypeIds::for_version(version))
+    }
+
+    pub fn with_ids(data: &'a [u8], ids: PacketTypeIds) -> Packets<'a> {
+        Packets {
+            data,
+            offset: 0,
+            failed: false,
+            ids,
+        }
+    }
+
+    /// Offset of the next packet in the stream
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Decodes the packets of the given type and skips the others
+    fn decode<T>(self, packet_type: PacketType) -> impl Iterator<Item = Result<T, PacketError>> + 'a
+    where
+        T: for<'b> TryFrom<&'b Packet<'a>, Error = PacketError> + 'a,
+    {
+        self.filter_map(move |packet| match packet {
+            Ok(p) if p.kind == packet_type => Some(T::try_from(&p)),
+            Ok(_) => None,
+            Err(e) => Some(Err(e)),
+        })
+    }
+
+    /// Decodes the position update packets and skips the others
+    pub fn position_updates(
+        self,
+    ) -> impl Iterator<Item = Result<PositionUpdate, PacketError>> + 'a {
+        self.decode(PacketType::PositionUpdate)
+    }
+
+    /// Decodes the chat message packets and skips the others
+    pub fn chat_messages(self) -> impl Iterator<Item = Result<ChatMessage, PacketError>> + 'a {
+        self.decode(PacketType::ChatMessage)
+    }
+
+    /// Decodes the battle events from the entity method calls and property updates
+    pub fn battle_events(
+        self,
+        definitions: EntityDefinitions,
+    ) -> impl Iterator<Item = Result<BattleEvent, PacketError>> + 'a {
+        let mut decoder = EventDecoder::new(definitions);
+        self.flat_map(
+            move |packet| match packet.and_then(|p| decoder.decode(&p)) {
+                Ok(events) => events.into_iter().map(Ok).collect::<Vec<_>>(),
+                Err(e) => vec![Err(e)],
+            },
+        )
+    }
+}
+
+impl<'a> Iterator for Packets<'a> {
+    type Item = Result<Packet<'a>, PacketError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed || self.offset >= self.data.len() {
+            return None;
+        }
+        let offset = self.offset;
+        let remain = &self.data[offset..];
+        if remain.len() < PACKET_HEADER_SIZE {
+            self.failed = true;
+            return Some(Err(PacketError::TruncatedHeader {
+                offset,
+                available: remain.len(),
+            }));
+        }
+        let length = LittleEndian::read_u32(&remain[0..4]);
+        let packet_type = LittleEndian::read_u32(&remain[4..8]);
+        let clock = LittleEndian::read_f32(&remain[8..12]);
+        let available = remain.len() - PACKET_HEADER_SIZE;
+        if length as usize > available {
+            self.failed = true;
+            return Some(Err(PacketError::TruncatedPayload {
+                offset,
+                length,
+                available,
+            }));
+        }
+        trace!(
+            "packet at offset {}: type {:#x}, length {}, clock {}",
+            offset,
+            packet_type,
+            length,
+            clock
+        );
+        let end = PACKET_HEADER_SIZE + length as usize;
+        self.offset += end;
+        Some(Ok(Packet {
+            length,
+            packet_type,
+            kind: self.ids.kind(packet_type),
+            clock,
+            payload: &remain[PACKET_HEADER_SIZE..end],
+        }))
+    }
+}
+
+impl<'a> std::iter::FusedIterator for Packets<'a> {}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Frames the payload the same way the game client does
+    pub(crate) fn frame(packet_type: u32, clock: f32, payload: &[u8]) -> Vec<u8> {
+        let mut result = vec![0u8; PACKET_HEADER_SIZE];
+        LittleEndian::write_u32(&mut result[0..4], payload.len() as u32);
+        LittleEndian::write_u32(&mut result[4..8], packet_type);
+        LittleEndian::write_f32(&mut result[8..12], clock);
+        result.extend_from_slice(payload);
+        result
+    }
+
+    #[test]
+    fn test_packets() {
+        let mut stream = frame(0x0a, 1.5, &[1, 2, 3]);
+        stream.extend(frame(0x08, 2.0, &[]));
+        let packets: Vec<_> = Packets::new(&stream).collect::<Result<_, _>>().unwrap();
+        assert_eq!(
+            packets,
+            vec![
+                Packet {
+                    length: 3,
+                    packet_type: 0x0a,
+                    kind: PacketType::PositionUpdate,
+                    clock: 1.5,
+                    payload: &[1, 2, 3],
+                },
+                Packet {
+                    length: 0,
+                    packet_type: 0x08,
+                    kind: PacketType::EntityMethod,
+                    clock: 2.0,
+                    payload: &[],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_packet_type_ids_for_version() {
+        let stream = frame(0x1f, 1.5, &[]);
+        let packet = Packets::new(&stream).next().unwrap().unwrap();
+        assert_eq!(packet.kind, PacketType::Unknown(0x1f));
+        let version = ClientVersion::new(0, 8, 11, 0);
+        let packet = Packets::with_version(&stream, &version)
+            .next()
+            .unwrap()
+            .unwrap();
+        assert_eq!(packet.kind, PacketType::ChatMessage);
+    }
+
+    #[test]
+    fn test_packets_malformed_framing() {
+        let mut stream = frame(0x0a, 1.5, &[1, 2, 3]);
+        stream.extend_from_slice(&[0, 0]);
+        let mut packets = Packets::new(&stream);
+        assert!(packets.next().unwrap().is_ok());
+        assert_eq!(
+            packets.next(),
+            Some(Err(PacketError::TruncatedHeader {
+                offset: 15,
+                available: 2
+            }))
+        );
+        assert_eq!(packets.next(), None);
+
+        let stream = frame(0x0a, 1.5, &[1, 2, 3]);
+        let mut packets = Packets::new(&stream[..14]);
+        assert_eq!(
+            packets.next(),
+            Some(Err(PacketError::TruncatedPayload {
+                offset: 0,
+                length: 3,
+                available: 2
+            }))
+        );
+        assert_eq!(packets.next(), None);
+    }
+}
@@ -0,0 +1,200 @@
+use super::{Packet, PacketError, PacketType};
+use byteorder::{ByteOrder, LittleEndian};
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+
+/// Font color of the sender in the team chat
+pub const TEAM_CHAT_COLOR: &str = "#80D63A";
+/// Font color of the sender in the squad chat
+pub const SQUAD_CHAT_COLOR: &str = "#FFB964";
+/// Font color of the sender in the all (common) chat
+pub const ALL_CHAT_COLOR: &str = "#FFC697";
+
+/// The chat channel of the message. It's determined by the font color of the sender
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum ChatChannel {
+    Team,
+    Squad,
+    All,
+    /// Unknown channel with the font color of the sender
+    Unknown(String),
+}
+
+impl From<&str> for ChatChannel {
+    fn from(color: &str) -> Self {
+        match color.to_uppercase().as_str() {
+            TEAM_CHAT_COLOR => ChatChannel::Team,
+            SQUAD_CHAT_COLOR => ChatChannel::Squad,
+            ALL_CHAT_COLOR => ChatChannel::All,
+            _ => ChatChannel::Unknown(color.to_string()),
+        }
+    }
+}
+
+/// In-battle chat message.
+///
+/// The game client records the message as an HTML snippet like
+/// `<font color='#80D63A'>Name[CLAN] (Vehicle)&nbsp;:&nbsp;</font><font color='#FFFFFF'>text</font>`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ChatMessage {
+    /// Game clock of the message
+    pub clock: f32,
+    /// Name of the sender
+    pub sender: String,
+    /// Clan abbreviation of the sender
+    pub sender_clan: Option<String>,
+    /// Vehicle name of the sender
+    pub sender_vehicle: Option<String>,
+    pub channel: ChatChannel,
+    /// Text of the message without HTML markup
+    pub text: String,
+}
+
+impl<'a> TryFrom<&Packet<'a>> for ChatMessage {
+    type Error = PacketError;
+
+    fn try_from(packet: &Packet<'a>) -> Result<Self, Self::Error> {
+        packet.expect_type(PacketType::ChatMessage, 4)?;
+        let length = LittleEndian::read_u32(&packet.payload[0..4]) as usize;
+        let html = packet
+            .payload
+            .get(4..4 + length)
+            .ok_or(PacketError::PayloadTooShort {
+                packet_type: packet.packet_type,
+                length: packet.length,
+                expected: 4 + length,
+            })?;
+        let html = String::from_utf8_lossy(html);
+        let fonts = split_fonts(&html);
+        let (color, header) = fonts.first().cloned().unwrap_or_default();
+        let text = fonts
+            .iter()
+            .skip(1)
+            .map(|(_, text)| text.as_str())
+            .collect::<String>();
+        let (sender, sender_clan, sender_vehicle) = parse_sender(&header);
+        Ok(ChatMessage {
+            clock: packet.clock,
+            sender,
+            sender_clan,
+            sender_vehicle,
+            channel: ChatChannel::from(color.as_str()),
+            text,
+        })
+    }
+}
+
+/// Splits the HTML snippet into the `(color, text)` pairs of the `<font>` tags
+fn split_fonts(html: &str) -> Vec<(String, String)> {
+    let mut result = vec![];
+    for part in html.split("<font").skip(1) {
+        let (attributes, body) = match part.find('>') {
+            Some(i) => (&part[..i], &part[i + 1..]),
+            None => continue,
+        };
+        let color = attributes
+            .split("color=")
+            .nth(1)
+            .map(|v| {
+                v.trim_start_matches(['\'', '"'])
+                    .chars()
+                    .take_while(|c| *c != '\'' && *c != '"' && !c.is_whitespace())
+                    .collect()
+            })
+            .unwrap_or_default();
+        let body = body.split("</font>").next().unwrap_or_default();
+        result.push((color, unescape(body)));
+    }
+    result
+}
+
+/// Parses the `Name[CLAN] (Vehicle) : ` message header
+fn parse_sender(header: &str) -> (String, Option<String>, Option<String>) {
+    let header = header.trim().trim_end_matches(':').trim();
+    let (header, vehicle) = match (header.find(" ("), header.ends_with(')')) {
+        (Some(i), true) => (
+            &header[..i],
+            Some(header[i + 2..header.len() - 1].to_string()),
+        ),
+        _ => (header, None),
+    };
+    let (name, clan) = match (header.find('['), header.ends_with(']')) {
+        (Some(i), true) => (
+            &header[..i],
+            Some(header[i + 1..header.len() - 1].to_string()),
+        ),
+        _ => (header, None),
+    };
+    (name.trim().to_string(), clan, vehicle)
+}
+
+fn unescape(text: &str) -> String {
+    text.replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::frame;
+    use super::super::Packets;
+    use super::*;
+
+    fn chat_payload(html: &str) -> Vec<u8> {
+        let mut payload = vec![0u8; 4];
+        LittleEndian::write_u32(&mut payload, html.len() as u32);
+        payload.extend_from_slice(html.as_bytes());
+        payload
+    }
+
+    #[test]
+    fn test_chat_messages() {
+        let mut stream = frame(
+            0x23,
+            125.5,
+            &chat_payload(
+                "<font color='#80D63A'>Player_1[CLAN] (LT-432)&nbsp;:&nbsp;</font>\
+                 <font color='#FFFFFF'>go A &amp; B</font>",
+            ),
+        );
+        stream.extend(frame(0x0a, 126.0, &[0; 49]));
+        stream.extend(frame(
+            0x23,
+            130.0,
+            &chat_payload(
+                "<font color='#FFC697'>Player_2 (Bat.-Châtillon 25 t)&nbsp;:&nbsp;</font>\
+                 <font color='#FFFFFF'>gl hf</font>",
+            ),
+        ));
+
+        let messages: Vec<_> = Packets::new(&stream)
+            .chat_messages()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(
+            messages,
+            vec![
+                ChatMessage {
+                    clock: 125.5,
+                    sender: "Player_1".to_string(),
+                    sender_clan: Some("CLAN".to_string()),
+                    sender_vehicle: Some("LT-432".to_string()),
+                    channel: ChatChannel::Team,
+                    text: "go A & B".to_string(),
+                },
+                ChatMessage {
+                    clock: 130.0,
+                    sender: "Player_2".to_string(),
+                    sender_clan: None,
+                    sender_vehicle: Some("Bat.-Châtillon 25 t".to_string()),
+                    channel: ChatChannel::All,
+                    text: "gl hf".to_string(),
+                },
+            ]
+        );
+    }
+}
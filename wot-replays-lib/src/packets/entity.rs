@@ -0,0 +1,73 @@
+use super::reader::PayloadReader;
+use super::{Packet, PacketError, PacketType};
+use std::convert::TryFrom;
+
+/// Size of the entity message header: entity id, message id and arguments length
+pub const ENTITY_MESSAGE_HEADER_SIZE: usize = 12;
+
+/// Call of the client method of the entity (e.g. `Vehicle.onHealthChanged`)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EntityMethod<'a> {
+    /// Game clock of the call
+    pub clock: f32,
+    pub entity_id: u32,
+    /// Index of the method in the client entity definition
+    pub method_id: u32,
+    /// Serialized method arguments
+    pub args: &'a [u8],
+}
+
+/// Update of the client property of the entity (e.g. `Vehicle.health`)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EntityProperty<'a> {
+    /// Game clock of the update
+    pub clock: f32,
+    pub entity_id: u32,
+    /// Index of the property in the client entity definition
+    pub property_id: u32,
+    /// Serialized property value
+    pub value: &'a [u8],
+}
+
+/// Reads the `(entity id, message id, data)` triple shared by the entity packets
+fn read_entity_message<'a>(
+    packet: &Packet<'a>,
+    packet_type: PacketType,
+) -> Result<(u32, u32, &'a [u8]), PacketError> {
+    packet.expect_type(packet_type, ENTITY_MESSAGE_HEADER_SIZE)?;
+    let mut reader = PayloadReader::new(packet.packet_type, packet.payload);
+    let entity_id = reader.read_u32()?;
+    let message_id = reader.read_u32()?;
+    let length = reader.read_u32()?;
+    let data = reader.read_bytes(length as usize)?;
+    Ok((entity_id, message_id, data))
+}
+
+impl<'a> TryFrom<&Packet<'a>> for EntityMethod<'a> {
+    type Error = PacketError;
+
+    fn try_from(packet: &Packet<'a>) -> Result<Self, Self::Error> {
+        let (entity_id, method_id, args) = read_entity_message(packet, PacketType::EntityMethod)?;
+        Ok(EntityMethod {
+            clock: packet.clock,
+            entity_id,
+            method_id,
+            args,
+        })
+    }
+}
+
+impl<'a> TryFrom<&Packet<'a>> for EntityProperty<'a> {
+    type Error = PacketError;
+
+    fn try_from(packet: &Packet<'a>) -> Result<Self, Self::Error> {
+        let (entity_id, property_id, value) =
+            read_entity_message(packet, PacketType::EntityProperty)?;
+        Ok(EntityProperty {
+            clock: packet.clock,
+            entity_id,
+            property_id,
+            value,
+        })
+    }
+}
@@ -0,0 +1,493 @@
+use super::entity::{EntityMethod, EntityProperty};
+use super::reader::PayloadReader;
+use super::{Packet, PacketError, PacketType};
+use crate::dictionaries::{BattleEventType, DeathReason};
+use crate::version::ClientVersion;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::convert::TryFrom;
+
+/// Size of the `Avatar.onBattleEvents` record: type, target id and details
+const AVATAR_BATTLE_EVENT_SIZE: usize = 13;
+
+/// Indexes and argument layouts of the entity methods and properties the battle events
+/// are decoded from.
+///
+/// The definitions come from the client entity definitions (`.def` files) and may change
+/// between the game patches. The defaults target the 1.12 clients.
+/// `EntityDefinitions::for_version` knows only the absence of the `oldHealth` argument
+/// before 1.10, the method and property indexes of the other clients have to be passed to
+/// `RawReplay::battle_events_with_definitions`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EntityDefinitions {
+    /// `Vehicle.health` property
+    pub vehicle_health: u32,
+    /// `Vehicle.onHealthChanged(newHealth, oldHealth, attackerID, attackReasonID)` method
+    pub vehicle_on_health_changed: u32,
+    /// `Vehicle.showDamageFromShot(attackerID, points, effectsIndex, damageFactor)` method
+    pub vehicle_show_damage_from_shot: u32,
+    /// `Avatar.onBattleEvents(events)` method
+    pub avatar_on_battle_events: u32,
+    /// `Vehicle.onHealthChanged` has the `oldHealth` argument
+    pub health_changed_with_old_health: bool,
+}
+
+impl EntityDefinitions {
+    /// Returns the entity definitions of the client version
+    pub fn for_version(version: &ClientVersion) -> EntityDefinitions {
+        if version.without_revision() < ClientVersion::new(1, 10, 0, 0) {
+            EntityDefinitions {
+                health_changed_with_old_health: false,
+                ..EntityDefinitions::default()
+            }
+        } else {
+            EntityDefinitions::default()
+        }
+    }
+}
+
+impl Default for EntityDefinitions {
+    fn default() -> Self {
+        EntityDefinitions {
+            vehicle_health: 2,
+            vehicle_on_health_changed: 2,
+            vehicle_show_damage_from_shot: 0,
+            avatar_on_battle_events: 72,
+            health_changed_with_old_health: true,
+        }
+    }
+}
+
+/// A single record of the `Avatar.onBattleEvents` call
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct AvatarBattleEvent {
+    pub event_type: BattleEventType,
+    /// Vehicle id of the event target
+    pub target_id: i32,
+    /// Packed event details, e.g. the amount of damage
+    pub details: u64,
+}
+
+/// Typed in-battle event decoded from the entity method calls and property updates
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum BattleEvent {
+    /// The vehicle health was updated without the damage, e.g. the initial health or the
+    /// repair
+    HealthChanged {
+        clock: f32,
+        vehicle_id: u32,
+        health: i16,
+    },
+    /// The vehicle health decreased
+    Damaged {
+        clock: f32,
+        vehicle_id: u32,
+        attacker_id: i32,
+        /// The health before the damage. Older clients don't send it, it's the last known
+        /// health of the vehicle then.
+        old_health: i16,
+        new_health: i16,
+        reason: Option<DeathReason>,
+    },
+    /// The vehicle was hit by a shot
+    DamageFromShot {
+        clock: f32,
+        vehicle_id: u32,
+        attacker_id: i32,
+        effects_index: u8,
+        damage_factor: u8,
+    },
+    /// The vehicle was destroyed
+    VehicleKilled {
+        clock: f32,
+        vehicle_id: u32,
+        killer_id: i32,
+        reason: Option<DeathReason>,
+    },
+    /// The recording player's personal battle events (spotting, assists, damage, ...)
+    BattleEvents {
+        clock: f32,
+        events: Vec<AvatarBattleEvent>,
+    },
+}
+
+/// Stateful decoder of the battle events. It tracks the recording player's avatar entity
+/// to tell the avatar messages from the vehicle ones and the last known health of the
+/// vehicles to tell the damage from the repair.
+#[derive(Debug, Clone, Default)]
+pub struct EventDecoder {
+    definitions: EntityDefinitions,
+    avatar_id: Option<u32>,
+    health: HashMap<u32, i16>,
+}
+
+impl EventDecoder {
+    pub fn new(definitions: EntityDefinitions) -> EventDecoder {
+        EventDecoder {
+            definitions,
+            avatar_id: None,
+            health: HashMap::new(),
+        }
+    }
+
+    /// Decodes the events of the packet. Unknown packets, methods and properties give
+    /// no events.
+    pub fn decode(&mut self, packet: &Packet) -> Result<Vec<BattleEvent>, PacketError> {
+        match packet.kind {
+            PacketType::BasePlayerCreate => {
+                let mut reader = PayloadReader::new(packet.packet_type, packet.payload);
+                self.avatar_id = Some(reader.read_u32()?);
+                Ok(vec![])
+            }
+            PacketType::EntityProperty => {
+                self.decode_property(packet.packet_type, &EntityProperty::try_from(packet)?)
+            }
+            PacketType::EntityMethod => {
+                self.decode_method(packet.packet_type, &EntityMethod::try_from(packet)?)
+            }
+            _ => Ok(vec![]),
+        }
+    }
+
+    fn is_avatar(&self, entity_id: u32) -> bool {
+        self.avatar_id == Some(entity_id)
+    }
+
+    fn decode_property(
+        &mut self,
+        packet_type: u32,
+        property: &EntityProperty,
+    ) -> Result<Vec<BattleEvent>, PacketError> {
+        if self.is_avatar(property.entity_id)
+            || property.property_id != self.definitions.vehicle_health
+        {
+            return Ok(vec![]);
+        }
+        let mut reader = PayloadReader::new(packet_type, property.value);
+        let health = reader.read_i16()?;
+        self.health.insert(property.entity_id, health);
+        Ok(vec![BattleEvent::HealthChanged {
+            clock: property.clock,
+            vehicle_id: property.entity_id,
+            health,
+        }])
+    }
+
+    fn decode_method(
+        &mut self,
+        packet_type: u32,
+        method: &EntityMethod,
+    ) -> Result<Vec<BattleEvent>, PacketError> {
+        let mut reader = PayloadReader::new(packet_type, method.args);
+        let clock = method.clock;
+        if self.is_avatar(method.entity_id) {
+            if method.method_id != self.definitions.avatar_on_battle_events {
+                return Ok(vec![]);
+            }
+            let count = reader.read_packed_length()?;
+            // The count is not trusted, a short payload can't hold more events
+            let mut events =
+                Vec::with_capacity(count.min(reader.remaining() / AVATAR_BATTLE_EVENT_SIZE));
+            for _ in 0..count {
+                events.push(AvatarBattleEvent {
+                    event_type: BattleEventType::from(reader.read_u8()?),
+                    target_id: reader.read_i32()?,
+                    details: reader.read_u64()?,
+                });
+            }
+            return Ok(vec![BattleEvent::BattleEvents { clock, events }]);
+        }
+
+        let vehicle_id = method.entity_id;
+        if method.method_id == self.definitions.vehicle_on_health_changed {
+            let new_health = reader.read_i16()?;
+            let old_health = if self.definitions.health_changed_with_old_health {
+                Some(reader.read_i16()?)
+            } else {
+                self.health.get(&vehicle_id).copied()
+            };
+            let attacker_id = reader.read_i32()?;
+            let reason = DeathReason::try_from(reader.read_i8()?).ok();
+            self.health.insert(vehicle_id, new_health);
+            let mut events = match old_health {
+                Some(old_health) if new_health < old_health => vec![BattleEvent::Damaged {
+                    clock,
+                    vehicle_id,
+                    attacker_id,
+                    old_health,
+                    new_health,
+                    reason,
+                }],
+                _ => vec![BattleEvent::HealthChanged {
+                    clock,
+                    vehicle_id,
+                    health: new_health,
+                }],
+            };
+            if new_health <= 0 && old_health.is_none_or(|h| h > 0) {
+                events.push(BattleEvent::VehicleKilled {
+                    clock,
+                    vehicle_id,
+                    killer_id: attacker_id,
+                    reason,
+                });
+            }
+            Ok(events)
+        } else if method.method_id == self.definitions.vehicle_show_damage_from_shot {
+            let attacker_id = reader.read_i32()?;
+            let points = reader.read_packed_length()?;
+            reader.read_bytes(points * 8)?;
+            Ok(vec![BattleEvent::DamageFromShot {
+                clock,
+                vehicle_id,
+                attacker_id,
+                effects_index: reader.read_u8()?,
+                damage_factor: reader.read_u8()?,
+            }])
+        } else {
+            Ok(vec![])
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::frame;
+    use super::super::Packets;
+    use super::*;
+    use byteorder::{LittleEndian, WriteBytesExt};
+
+    fn entity_message(entity_id: u32, message_id: u32, data: &[u8]) -> Vec<u8> {
+        let mut payload = vec![];
+        payload.write_u32::<LittleEndian>(entity_id).unwrap();
+        payload.write_u32::<LittleEndian>(message_id).unwrap();
+        payload
+            .write_u32::<LittleEndian>(data.len() as u32)
+            .unwrap();
+        payload.extend_from_slice(data);
+        payload
+    }
+
+    #[test]
+    fn test_battle_events() {
+        let ids = EntityDefinitions::default();
+        let avatar_id: u32 = 100;
+        let vehicle_id: u32 = 200;
+        let attacker_id: i32 = 300;
+
+        let mut stream = frame(0x00, 0.0, &avatar_id.to_le_bytes());
+        stream.extend(frame(
+            0x07,
+            1.0,
+            &entity_message(vehicle_id, ids.vehicle_health, &1000i16.to_le_bytes()),
+        ));
+        let mut args = vec![];
+        args.write_i16::<LittleEndian>(-10).unwrap();
+        args.write_i16::<LittleEndian>(250).unwrap();
+        args.write_i32::<LittleEndian>(attacker_id).unwrap();
+        args.write_i8(0).unwrap();
+        stream.extend(frame(
+            0x08,
+            2.0,
+            &entity_message(vehicle_id, ids.vehicle_on_health_changed, &args),
+        ));
+        let mut args = vec![1u8, 7];
+        args.write_i32::<LittleEndian>(vehicle_id as i32).unwrap();
+        args.write_u64::<LittleEndian>(390).unwrap();
+        stream.extend(frame(
+            0x08,
+            2.5,
+            &entity_message(avatar_id, ids.avatar_on_battle_events, &args),
+        ));
+
+        let events: Vec<_> = Packets::new(&stream)
+            .battle_events(ids)
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(
+            events,
+            vec![
+                BattleEvent::HealthChanged {
+                    clock: 1.0,
+                    vehicle_id,
+                    health: 1000
+                },
+                BattleEvent::Damaged {
+                    clock: 2.0,
+                    vehicle_id,
+                    attacker_id,
+                    old_health: 250,
+                    new_health: -10,
+                    reason: Some(DeathReason::Shot),
+                },
+                BattleEvent::VehicleKilled {
+                    clock: 2.0,
+                    vehicle_id,
+                    killer_id: attacker_id,
+                    reason: Some(DeathReason::Shot),
+                },
+                BattleEvent::BattleEvents {
+                    clock: 2.5,
+                    events: vec![AvatarBattleEvent {
+                        event_type: BattleEventType::Damage,
+                        target_id: vehicle_id as i32,
+                        details: 390,
+                    }],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_health_increase() {
+        let ids = EntityDefinitions::default();
+        let mut args = vec![];
+        args.write_i16::<LittleEndian>(300).unwrap();
+        args.write_i16::<LittleEndian>(250).unwrap();
+        args.write_i32::<LittleEndian>(0).unwrap();
+        args.write_i8(-1).unwrap();
+        let stream = frame(
+            0x08,
+            3.0,
+            &entity_message(200, ids.vehicle_on_health_changed, &args),
+        );
+        let events: Vec<_> = Packets::new(&stream)
+            .battle_events(ids)
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(
+            events,
+            vec![BattleEvent::HealthChanged {
+                clock: 3.0,
+                vehicle_id: 200,
+                health: 300
+            }]
+        );
+    }
+
+    #[test]
+    fn test_battle_events_of_older_clients() {
+        let definitions = EntityDefinitions::for_version(&ClientVersion::new(1, 9, 1, 0));
+        assert!(!definitions.health_changed_with_old_health);
+
+        let health_changed = |new_health: i16| {
+            let mut args = vec![];
+            args.write_i16::<LittleEndian>(new_health).unwrap();
+            args.write_i32::<LittleEndian>(300).unwrap();
+            args.write_i8(1).unwrap();
+            entity_message(200, definitions.vehicle_on_health_changed, &args)
+        };
+        let mut stream = frame(
+            0x07,
+            1.0,
+            &entity_message(200, definitions.vehicle_health, &100i16.to_le_bytes()),
+        );
+        stream.extend(frame(0x08, 2.0, &health_changed(40)));
+        stream.extend(frame(0x08, 3.0, &health_changed(0)));
+        let events: Vec<_> = Packets::new(&stream)
+            .battle_events(definitions.clone())
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(events.len(), 4);
+        assert_eq!(
+            events[1],
+            BattleEvent::Damaged {
+                clock: 2.0,
+                vehicle_id: 200,
+                attacker_id: 300,
+                old_health: 100,
+                new_health: 40,
+                reason: Some(DeathReason::Fire),
+            }
+        );
+        assert_eq!(
+            events[3],
+            BattleEvent::VehicleKilled {
+                clock: 3.0,
+                vehicle_id: 200,
+                killer_id: 300,
+                reason: Some(DeathReason::Fire),
+            }
+        );
+
+        // The health before the damage is unknown
+        let stream = frame(0x08, 2.0, &health_changed(0));
+        let events: Vec<_> = Packets::new(&stream)
+            .battle_events(definitions)
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(
+            events[0],
+            BattleEvent::HealthChanged {
+                clock: 2.0,
+                vehicle_id: 200,
+                health: 0
+            }
+        );
+        assert!(matches!(events[1], BattleEvent::VehicleKilled { .. }));
+    }
+
+    #[test]
+    fn test_hostile_battle_events_count() {
+        let ids = EntityDefinitions::default();
+        let mut stream = frame(0x00, 0.0, &100u32.to_le_bytes());
+        stream.extend(frame(
+            0x08,
+            1.0,
+            &entity_message(100, ids.avatar_on_battle_events, &[0xFF, 0xFF, 0xFF, 0xFF]),
+        ));
+        let result: Result<Vec<_>, _> = Packets::new(&stream).battle_events(ids).collect();
+        assert!(matches!(result, Err(PacketError::PayloadTooShort { .. })));
+    }
+
+    /// Checks the entity definitions against the real battle streams: the vehicles killed
+    /// and the damage dealt in the stream must match the battle results of the replay.
+    #[test]
+    #[ignore = "needs the git-lfs content of test_data/*.wotreplay"]
+    fn test_battle_events_of_replay_files() {
+        use crate::models::{RawReplay, Replay};
+
+        for (path, content) in crate::models::tests::test_data_replays() {
+            let raw_replay = RawReplay::read(&mut &content[..]).unwrap();
+            let results = match Replay::try_from(&raw_replay).unwrap().results {
+                Some(results) => results,
+                None => continue,
+            };
+            let mut killed = vec![];
+            let mut damage_dealt: HashMap<i32, u32> = HashMap::new();
+            for event in raw_replay.battle_events().unwrap() {
+                match event {
+                    BattleEvent::VehicleKilled { vehicle_id, .. } => killed.push(vehicle_id),
+                    BattleEvent::Damaged {
+                        attacker_id,
+                        old_health,
+                        new_health,
+                        ..
+                    } => {
+                        *damage_dealt.entry(attacker_id).or_default() +=
+                            (old_health - new_health.max(0)) as u32;
+                    }
+                    _ => {}
+                }
+            }
+            killed.sort_unstable();
+            let mut expected_killed = vec![];
+            for (vehicle_id, vehicles) in &results.general.vehicles {
+                let vehicle_id: u32 = vehicle_id.parse().unwrap();
+                let vehicle = &vehicles[0];
+                if vehicle.health <= 0 {
+                    expected_killed.push(vehicle_id);
+                }
+                assert_eq!(
+                    damage_dealt.get(&(vehicle_id as i32)).copied().unwrap_or(0),
+                    vehicle.damage_dealt,
+                    "{:?}: damage dealt by {}",
+                    path,
+                    vehicle_id
+                );
+            }
+            expected_killed.sort_unstable();
+            assert_eq!(killed, expected_killed, "{:?}: killed vehicles", path);
+        }
+    }
+}
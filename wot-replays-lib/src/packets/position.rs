@@ -0,0 +1,108 @@
+use super::{Packet, PacketError, PacketType};
+use byteorder::{ByteOrder, LittleEndian};
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+
+/// Size of the position update payload
+pub const POSITION_UPDATE_SIZE: usize = 49;
+
+/// A point or a vector in the world space
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+pub struct Vector3 {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+}
+
+impl Vector3 {
+    fn read(buf: &[u8]) -> Vector3 {
+        Vector3 {
+            x: LittleEndian::read_f32(&buf[0..4]),
+            y: LittleEndian::read_f32(&buf[4..8]),
+            z: LittleEndian::read_f32(&buf[8..12]),
+        }
+    }
+}
+
+/// Per-tick update of the vehicle position and hull orientation
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct PositionUpdate {
+    /// Game clock of the update
+    pub clock: f32,
+    /// Id of the vehicle entity. Matches the keys of `BattleInfo.vehicles`
+    pub entity_id: u32,
+    pub space_id: u32,
+    pub vehicle_id: u32,
+    /// Position of the vehicle in the world space
+    pub position: Vector3,
+    pub position_error: Vector3,
+    /// Hull yaw in radians
+    pub yaw: f32,
+    /// Hull pitch in radians
+    pub pitch: f32,
+    /// Hull roll in radians
+    pub roll: f32,
+    pub is_error: bool,
+}
+
+impl<'a> TryFrom<&Packet<'a>> for PositionUpdate {
+    type Error = PacketError;
+
+    fn try_from(packet: &Packet<'a>) -> Result<Self, Self::Error> {
+        packet.expect_type(PacketType::PositionUpdate, POSITION_UPDATE_SIZE)?;
+        let payload = packet.payload;
+        Ok(PositionUpdate {
+            clock: packet.clock,
+            entity_id: LittleEndian::read_u32(&payload[0..4]),
+            space_id: LittleEndian::read_u32(&payload[4..8]),
+            vehicle_id: LittleEndian::read_u32(&payload[8..12]),
+            position: Vector3::read(&payload[12..24]),
+            position_error: Vector3::read(&payload[24..36]),
+            yaw: LittleEndian::read_f32(&payload[36..40]),
+            pitch: LittleEndian::read_f32(&payload[40..44]),
+            roll: LittleEndian::read_f32(&payload[44..48]),
+            is_error: payload[48] != 0,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::frame;
+    use super::super::Packets;
+    use super::*;
+
+    #[test]
+    fn test_position_update() {
+        let mut payload = vec![0u8; POSITION_UPDATE_SIZE];
+        LittleEndian::write_u32(&mut payload[0..4], 15452231);
+        LittleEndian::write_u32(&mut payload[8..12], 15452231);
+        LittleEndian::write_f32_into(&[-120.5, 12.25, 340.0], &mut payload[12..24]);
+        LittleEndian::write_f32_into(&[1.5, -0.25, 0.125], &mut payload[36..48]);
+        let mut stream = frame(0x08, 10.0, &[0; 4]);
+        stream.extend(frame(0x0a, 10.5, &payload));
+
+        let updates: Vec<_> = Packets::new(&stream)
+            .position_updates()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(updates.len(), 1);
+        let update = &updates[0];
+        assert_eq!(update.clock, 10.5);
+        assert_eq!(update.entity_id, 15452231);
+        assert_eq!(
+            update.position,
+            Vector3 {
+                x: -120.5,
+                y: 12.25,
+                z: 340.0
+            }
+        );
+        assert_eq!((update.yaw, update.pitch, update.roll), (1.5, -0.25, 0.125));
+        assert!(!update.is_error);
+
+        let stream = frame(0x0a, 10.5, &payload[..20]);
+        let packet = Packets::new(&stream).next().unwrap().unwrap();
+        assert!(PositionUpdate::try_from(&packet).is_err());
+    }
+}
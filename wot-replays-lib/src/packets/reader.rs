@@ -0,0 +1,72 @@
+use super::PacketError;
+use byteorder::{ByteOrder, LittleEndian};
+
+/// Sequential reader of the little-endian packet payload
+pub(crate) struct PayloadReader<'a> {
+    packet_type: u32,
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> PayloadReader<'a> {
+    pub(crate) fn new(packet_type: u32, data: &'a [u8]) -> PayloadReader<'a> {
+        PayloadReader {
+            packet_type,
+            data,
+            position: 0,
+        }
+    }
+
+    /// Number of the bytes left to read
+    pub(crate) fn remaining(&self) -> usize {
+        self.data.len().saturating_sub(self.position)
+    }
+
+    pub(crate) fn read_bytes(&mut self, length: usize) -> Result<&'a [u8], PacketError> {
+        let end = self.position + length;
+        let bytes = self
+            .data
+            .get(self.position..end)
+            .ok_or(PacketError::PayloadTooShort {
+                packet_type: self.packet_type,
+                length: self.data.len() as u32,
+                expected: end,
+            })?;
+        self.position = end;
+        Ok(bytes)
+    }
+
+    pub(crate) fn read_u8(&mut self) -> Result<u8, PacketError> {
+        Ok(self.read_bytes(1)?[0])
+    }
+
+    pub(crate) fn read_i8(&mut self) -> Result<i8, PacketError> {
+        Ok(self.read_u8()? as i8)
+    }
+
+    pub(crate) fn read_i16(&mut self) -> Result<i16, PacketError> {
+        Ok(LittleEndian::read_i16(self.read_bytes(2)?))
+    }
+
+    pub(crate) fn read_u32(&mut self) -> Result<u32, PacketError> {
+        Ok(LittleEndian::read_u32(self.read_bytes(4)?))
+    }
+
+    pub(crate) fn read_i32(&mut self) -> Result<i32, PacketError> {
+        Ok(LittleEndian::read_i32(self.read_bytes(4)?))
+    }
+
+    pub(crate) fn read_u64(&mut self) -> Result<u64, PacketError> {
+        Ok(LittleEndian::read_u64(self.read_bytes(8)?))
+    }
+
+    /// Reads the BigWorld packed length: one byte, or `0xFF` followed by three bytes
+    pub(crate) fn read_packed_length(&mut self) -> Result<usize, PacketError> {
+        let length = self.read_u8()?;
+        if length != 0xFF {
+            return Ok(length as usize);
+        }
+        let bytes = self.read_bytes(3)?;
+        Ok(LittleEndian::read_u24(bytes) as usize)
+    }
+}
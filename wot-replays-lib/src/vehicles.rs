@@ -0,0 +1,319 @@
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
+
+pub mod database;
+
+pub use database::{LocalVehicleDatabase, VehicleClass, VehicleDatabase, VehicleEntry};
+
+/// Item type id of the vehicles in the compact descriptors
+pub const VEHICLE_ITEM_TYPE: u8 = 1;
+
+/// Contains the game nations. The discriminants are the nation ids of the compact descriptors
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum Nation {
+    Ussr = 0,
+    Germany = 1,
+    Usa = 2,
+    China = 3,
+    France = 4,
+    Uk = 5,
+    Japan = 6,
+    Czech = 7,
+    Sweden = 8,
+    Poland = 9,
+    Italy = 10,
+}
+
+impl Nation {
+    pub const ALL: [Nation; 11] = [
+        Nation::Ussr,
+        Nation::Germany,
+        Nation::Usa,
+        Nation::China,
+        Nation::France,
+        Nation::Uk,
+        Nation::Japan,
+        Nation::Czech,
+        Nation::Sweden,
+        Nation::Poland,
+        Nation::Italy,
+    ];
+
+    /// Returns the nation name as the game uses it, e.g. `ussr`
+    pub fn name(&self) -> &'static str {
+        match self {
+            Nation::Ussr => "ussr",
+            Nation::Germany => "germany",
+            Nation::Usa => "usa",
+            Nation::China => "china",
+            Nation::France => "france",
+            Nation::Uk => "uk",
+            Nation::Japan => "japan",
+            Nation::Czech => "czech",
+            Nation::Sweden => "sweden",
+            Nation::Poland => "poland",
+            Nation::Italy => "italy",
+        }
+    }
+}
+
+impl TryFrom<u8> for Nation {
+    type Error = u8;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        Nation::ALL
+            .iter()
+            .find(|n| **n as u8 == value)
+            .copied()
+            .ok_or(value)
+    }
+}
+
+impl FromStr for Nation {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Nation::ALL
+            .iter()
+            .find(|n| n.name() == s)
+            .copied()
+            .ok_or("Unknown nation")
+    }
+}
+
+impl fmt::Display for Nation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Vehicle type name, e.g. `ussr:R158_LT_432`. Both the results form (`ussr:R158_LT_432`)
+/// and the `BattleInfo.player_vehicle` form (`ussr-R158_LT_432`) are parsed. It's
+/// serialized in the results form.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(try_from = "String", into = "String")]
+pub struct VehicleTag {
+    pub nation: Nation,
+    /// The vehicle tag without the nation, e.g. `R158_LT_432`
+    pub name: String,
+}
+
+impl VehicleTag {
+    pub fn new(nation: Nation, name: &str) -> VehicleTag {
+        VehicleTag {
+            nation,
+            name: name.to_string(),
+        }
+    }
+
+    /// Returns the tag in the `BattleInfo.player_vehicle` form, e.g. `ussr-R158_LT_432`
+    pub fn to_player_vehicle(&self) -> String {
+        format!("{}-{}", self.nation, self.name)
+    }
+}
+
+impl FromStr for VehicleTag {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let i = s.find([':', '-']).ok_or("No nation separator found")?;
+        let name = &s[i + 1..];
+        if name.is_empty() {
+            return Err("Empty vehicle name");
+        }
+        Ok(VehicleTag::new(s[..i].parse()?, name))
+    }
+}
+
+impl TryFrom<String> for VehicleTag {
+    type Error = &'static str;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<VehicleTag> for String {
+    fn from(tag: VehicleTag) -> Self {
+        tag.to_string()
+    }
+}
+
+impl fmt::Display for VehicleTag {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.nation, self.name)
+    }
+}
+
+/// Compact descriptor of the vehicle type (`typeCompDescr`), e.g. `45569`.
+///
+/// The descriptor packs the item type into the bits 0-3, the nation id into the bits 4-7
+/// and the vehicle id into the remaining bits. It's serialized as the packed value and
+/// deserialized from the packed value or its string form (the JSON object keys).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct VehicleTypeDescriptor {
+    pub item_type: u8,
+    pub nation_id: u8,
+    pub vehicle_id: u32,
+}
+
+impl VehicleTypeDescriptor {
+    /// Creates the descriptor of the vehicle item type
+    pub fn new(nation_id: u8, vehicle_id: u32) -> VehicleTypeDescriptor {
+        VehicleTypeDescriptor {
+            item_type: VEHICLE_ITEM_TYPE,
+            nation_id,
+            vehicle_id,
+        }
+    }
+
+    pub fn from_packed(packed: u32) -> VehicleTypeDescriptor {
+        VehicleTypeDescriptor {
+            item_type: (packed & 0x0F) as u8,
+            nation_id: ((packed >> 4) & 0x0F) as u8,
+            vehicle_id: packed >> 8,
+        }
+    }
+
+    pub fn packed(&self) -> u32 {
+        (self.vehicle_id << 8)
+            | ((self.nation_id as u32 & 0x0F) << 4)
+            | (self.item_type as u32 & 0x0F)
+    }
+
+    /// Returns the nation of the descriptor, `None` for the unknown nation ids
+    pub fn nation(&self) -> Option<Nation> {
+        Nation::try_from(self.nation_id).ok()
+    }
+
+    /// Whether the descriptor describes a vehicle and not another item
+    pub fn is_vehicle(&self) -> bool {
+        self.item_type == VEHICLE_ITEM_TYPE
+    }
+}
+
+impl From<u32> for VehicleTypeDescriptor {
+    fn from(packed: u32) -> Self {
+        VehicleTypeDescriptor::from_packed(packed)
+    }
+}
+
+impl From<VehicleTypeDescriptor> for u32 {
+    fn from(descriptor: VehicleTypeDescriptor) -> Self {
+        descriptor.packed()
+    }
+}
+
+impl Serialize for VehicleTypeDescriptor {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u32(self.packed())
+    }
+}
+
+impl<'de> Deserialize<'de> for VehicleTypeDescriptor {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct DescriptorVisitor;
+
+        impl<'de> serde::de::Visitor<'de> for DescriptorVisitor {
+            type Value = VehicleTypeDescriptor;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a packed vehicle type descriptor")
+            }
+
+            fn visit_u64<E: serde::de::Error>(self, v: u64) -> Result<Self::Value, E> {
+                u32::try_from(v)
+                    .map(VehicleTypeDescriptor::from_packed)
+                    .map_err(|_| E::invalid_value(serde::de::Unexpected::Unsigned(v), &self))
+            }
+
+            fn visit_i64<E: serde::de::Error>(self, v: i64) -> Result<Self::Value, E> {
+                u32::try_from(v)
+                    .map(VehicleTypeDescriptor::from_packed)
+                    .map_err(|_| E::invalid_value(serde::de::Unexpected::Signed(v), &self))
+            }
+
+            fn visit_str<E: serde::de::Error>(self, v: &str) -> Result<Self::Value, E> {
+                v.parse()
+                    .map_err(|_| E::invalid_value(serde::de::Unexpected::Str(v), &self))
+            }
+        }
+
+        deserializer.deserialize_any(DescriptorVisitor)
+    }
+}
+
+impl FromStr for VehicleTypeDescriptor {
+    type Err = std::num::ParseIntError;
+
+    /// Parses the descriptor from the string keys of the results, e.g. `"45569"`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(VehicleTypeDescriptor::from_packed(s.parse()?))
+    }
+}
+
+impl fmt::Display for VehicleTypeDescriptor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.packed())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vehicle_tag() {
+        let expected = VehicleTag::new(Nation::Ussr, "R158_LT_432");
+        let tag: VehicleTag = "ussr:R158_LT_432".parse().unwrap();
+        assert_eq!(tag, expected);
+        let tag: VehicleTag = "ussr-R158_LT_432".parse().unwrap();
+        assert_eq!(tag, expected);
+        assert_eq!(tag.to_string(), "ussr:R158_LT_432");
+        assert_eq!(tag.to_player_vehicle(), "ussr-R158_LT_432");
+        assert_eq!(
+            "france-F116_Bat_Chatillon_Bourrasque".parse::<VehicleTag>(),
+            Ok(VehicleTag::new(
+                Nation::France,
+                "F116_Bat_Chatillon_Bourrasque"
+            ))
+        );
+        assert_eq!(
+            serde_json::from_str::<VehicleTag>("\"usa:A83_T110E4\"").unwrap(),
+            VehicleTag::new(Nation::Usa, "A83_T110E4")
+        );
+        assert!("mars:X1".parse::<VehicleTag>().is_err());
+        assert!("ussr".parse::<VehicleTag>().is_err());
+        assert!("ussr:".parse::<VehicleTag>().is_err());
+    }
+
+    #[test]
+    fn test_vehicle_type_descriptor() {
+        let descriptor: VehicleTypeDescriptor = "45569".parse().unwrap();
+        assert_eq!(descriptor, VehicleTypeDescriptor::new(0, 178));
+        assert_eq!(descriptor.nation(), Some(Nation::Ussr));
+        assert!(descriptor.is_vehicle());
+        assert_eq!(descriptor.packed(), 45569);
+        assert_eq!(descriptor.to_string(), "45569");
+
+        let descriptor = VehicleTypeDescriptor::from(59169);
+        assert_eq!(descriptor, VehicleTypeDescriptor::new(2, 231));
+        assert_eq!(u32::from(descriptor), 59169);
+
+        let descriptor = VehicleTypeDescriptor::new(4, 1537);
+        assert_eq!(VehicleTypeDescriptor::from(descriptor.packed()), descriptor);
+        assert_eq!(serde_json::to_string(&descriptor).unwrap(), "393537");
+        assert_eq!(
+            serde_json::from_str::<VehicleTypeDescriptor>("393537").unwrap(),
+            descriptor
+        );
+        assert_eq!(
+            serde_json::from_str::<VehicleTypeDescriptor>("\"393537\"").unwrap(),
+            descriptor
+        );
+        assert!(serde_json::from_str::<VehicleTypeDescriptor>("\"avatar\"").is_err());
+    }
+}
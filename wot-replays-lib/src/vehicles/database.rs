@@ -0,0 +1,283 @@
+use super::{VehicleTag, VehicleTypeDescriptor};
+use crate::data::{extension, DataError, Keyed, Table};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs::File;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+
+/// The bundled encyclopedia snapshot. It's a stub with 25 of the vehicles of the
+/// `test_data` battle: the full encyclopedia is exported from the game client or the public
+/// API and is not redistributed with the crate.
+const BUNDLED_SNAPSHOT: &str = include_str!("../../data/vehicles.json");
+
+/// Contains the vehicle classes. They are (de)serialized as the game class tags.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum VehicleClass {
+    #[serde(rename = "lightTank")]
+    LightTank,
+    #[serde(rename = "mediumTank")]
+    MediumTank,
+    #[serde(rename = "heavyTank")]
+    HeavyTank,
+    #[serde(rename = "AT-SPG")]
+    AtSpg,
+    #[serde(rename = "SPG")]
+    Spg,
+}
+
+impl VehicleClass {
+    /// Returns the class tag as the game uses it, e.g. `lightTank`
+    pub fn name(&self) -> &'static str {
+        match self {
+            VehicleClass::LightTank => "lightTank",
+            VehicleClass::MediumTank => "mediumTank",
+            VehicleClass::HeavyTank => "heavyTank",
+            VehicleClass::AtSpg => "AT-SPG",
+            VehicleClass::Spg => "SPG",
+        }
+    }
+}
+
+impl FromStr for VehicleClass {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        [
+            VehicleClass::LightTank,
+            VehicleClass::MediumTank,
+            VehicleClass::HeavyTank,
+            VehicleClass::AtSpg,
+            VehicleClass::Spg,
+        ]
+        .iter()
+        .find(|c| c.name() == s)
+        .copied()
+        .ok_or("Unknown vehicle class")
+    }
+}
+
+impl fmt::Display for VehicleClass {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Encyclopedia attributes of the vehicle
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct VehicleEntry {
+    pub descriptor: VehicleTypeDescriptor,
+    pub tag: VehicleTag,
+    pub tier: u8,
+    pub class: VehicleClass,
+    #[serde(rename = "premium")]
+    pub is_premium: bool,
+    /// Localized name in the language of the data source, e.g. `LT-432`
+    pub name: String,
+}
+
+/// Source of the vehicle attributes.
+///
+/// `LocalVehicleDatabase::bundled` alone is not enough for the matchmaking, tier spread or
+/// WN8 calculations as it misses most of the vehicles, load the full encyclopedia export
+/// with `LocalVehicleDatabase::load` for them.
+pub trait VehicleDatabase {
+    fn by_descriptor(&self, descriptor: &VehicleTypeDescriptor) -> Option<&VehicleEntry>;
+
+    fn by_tag(&self, tag: &VehicleTag) -> Option<&VehicleEntry>;
+}
+
+/// In-memory vehicle database loaded from the bundled snapshot or a local file.
+///
+/// Both JSON and CSV sources use the `VehicleEntry` fields: `descriptor`, `tag`, `tier`,
+/// `class`, `premium` and `name`. The JSON source is an array of objects, the CSV source
+/// has a header row.
+#[derive(Debug, Clone, Default)]
+pub struct LocalVehicleDatabase {
+    table: Table<VehicleEntry>,
+}
+
+impl Keyed for VehicleEntry {
+    type Key = VehicleTypeDescriptor;
+    type AltKey = VehicleTag;
+
+    fn key(&self) -> &VehicleTypeDescriptor {
+        &self.descriptor
+    }
+
+    fn alt_key(&self) -> &VehicleTag {
+        &self.tag
+    }
+}
+
+impl LocalVehicleDatabase {
+    /// Creates the database of the entries. The later entries override the earlier ones.
+    pub fn from_entries(entries: impl IntoIterator<Item = VehicleEntry>) -> LocalVehicleDatabase {
+        LocalVehicleDatabase {
+            table: Table::from_entries(entries),
+        }
+    }
+
+    /// Loads the bundled encyclopedia snapshot. It's a stub with 25 of the vehicles of the
+    /// `test_data` battle, see `LocalVehicleDatabase::load` for the complete coverage.
+    pub fn bundled() -> LocalVehicleDatabase {
+        LocalVehicleDatabase {
+            table: Table::bundled(BUNDLED_SNAPSHOT),
+        }
+    }
+
+    pub fn from_json(reader: impl io::Read) -> Result<LocalVehicleDatabase, DataError> {
+        Ok(LocalVehicleDatabase {
+            table: Table::from_json(reader)?,
+        })
+    }
+
+    pub fn from_csv(reader: impl io::Read) -> Result<LocalVehicleDatabase, DataError> {
+        let entries = csv::Reader::from_reader(reader)
+            .deserialize()
+            .collect::<Result<Vec<VehicleEntry>, _>>()?;
+        Ok(Self::from_entries(entries))
+    }
+
+    /// Loads the database from the `.json` or `.csv` file
+    pub fn load(path: impl AsRef<Path>) -> Result<LocalVehicleDatabase, DataError> {
+        let path = path.as_ref();
+        match extension(path).as_deref() {
+            Some("json") => Self::from_json(io::BufReader::new(File::open(path)?)),
+            Some("csv") => Self::from_csv(File::open(path)?),
+            _ => Err(DataError::UnknownFormat(path.display().to_string())),
+        }
+    }
+
+    /// Adds the entry replacing the entries with the same descriptor or tag. The entry may
+    /// collide with two different entries, one by descriptor and another by tag, both are
+    /// removed.
+    pub fn insert(&mut self, entry: VehicleEntry) {
+        self.table.insert(entry);
+    }
+
+    /// Extends the database with the entries of another one, e.g. the bundled snapshot
+    /// with a local file
+    pub fn merge(&mut self, other: LocalVehicleDatabase) {
+        self.table.merge(other.table);
+    }
+
+    pub fn entries(&self) -> &[VehicleEntry] {
+        self.table.entries()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries().is_empty()
+    }
+}
+
+impl VehicleDatabase for LocalVehicleDatabase {
+    fn by_descriptor(&self, descriptor: &VehicleTypeDescriptor) -> Option<&VehicleEntry> {
+        self.table.get(descriptor)
+    }
+
+    fn by_tag(&self, tag: &VehicleTag) -> Option<&VehicleEntry> {
+        self.table.get_by_alt_key(tag)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vehicles::Nation;
+
+    #[test]
+    fn test_bundled_database() {
+        let database = LocalVehicleDatabase::bundled();
+        assert_eq!(database.len(), 25);
+        let entry = database
+            .by_descriptor(&VehicleTypeDescriptor::from_packed(45569))
+            .unwrap();
+        assert_eq!(entry.tag, VehicleTag::new(Nation::Ussr, "R158_LT_432"));
+        assert_eq!(entry.tier, 8);
+        assert_eq!(entry.class, VehicleClass::LightTank);
+        assert!(entry.is_premium);
+        assert_eq!(entry.name, "LT-432");
+        assert_eq!(
+            database.by_tag(&"ussr-R158_LT_432".parse().unwrap()),
+            Some(entry)
+        );
+        for entry in database.entries() {
+            assert_eq!(entry.descriptor.nation(), Some(entry.tag.nation));
+        }
+    }
+
+    #[test]
+    fn test_csv_database() {
+        let csv = "descriptor,tag,tier,class,premium,name\n\
+                   45569,ussr:R158_LT_432,8,lightTank,true,\"LT-432, premium\"\n\
+                   13089,usa:A83_T110E4,10,AT-SPG,false,T110E4\n";
+        let mut database = LocalVehicleDatabase::from_csv(csv.as_bytes()).unwrap();
+        assert_eq!(database.len(), 2);
+        let entry = database
+            .by_tag(&VehicleTag::new(Nation::Usa, "A83_T110E4"))
+            .unwrap();
+        assert_eq!(entry.class, VehicleClass::AtSpg);
+        assert_eq!(entry.tier, 10);
+
+        database.merge(LocalVehicleDatabase::bundled());
+        let entry = database
+            .by_descriptor(&VehicleTypeDescriptor::from_packed(45569))
+            .unwrap();
+        assert_eq!(entry.name, "LT-432");
+        assert_eq!(database.len(), LocalVehicleDatabase::bundled().len());
+
+        assert!(LocalVehicleDatabase::from_csv("descriptor,tag\n1,x".as_bytes()).is_err());
+        assert!(matches!(
+            LocalVehicleDatabase::load("vehicles.xml"),
+            Err(DataError::UnknownFormat(_))
+        ));
+    }
+
+    #[test]
+    fn test_insert_colliding_entries() {
+        let csv = "descriptor,tag,tier,class,premium,name\n\
+                   45569,ussr:R158_LT_432,8,lightTank,true,LT-432\n\
+                   13089,usa:A83_T110E4,10,AT-SPG,false,T110E4\n\
+                   1,germany:G00_Test,1,lightTank,false,Test\n";
+        let mut database = LocalVehicleDatabase::from_csv(csv.as_bytes()).unwrap();
+        // The descriptor of the first entry and the tag of the second one
+        let entry = VehicleEntry {
+            descriptor: VehicleTypeDescriptor::from_packed(45569),
+            tag: VehicleTag::new(Nation::Usa, "A83_T110E4"),
+            tier: 10,
+            class: VehicleClass::AtSpg,
+            is_premium: false,
+            name: "Renamed".to_string(),
+        };
+        database.insert(entry.clone());
+        assert_eq!(database.len(), 2);
+        assert_eq!(
+            database.by_descriptor(&VehicleTypeDescriptor::from_packed(45569)),
+            Some(&entry)
+        );
+        assert_eq!(
+            database.by_tag(&VehicleTag::new(Nation::Usa, "A83_T110E4")),
+            Some(&entry)
+        );
+        assert_eq!(
+            database.by_descriptor(&VehicleTypeDescriptor::from_packed(13089)),
+            None
+        );
+        assert_eq!(
+            database.by_tag(&VehicleTag::new(Nation::Ussr, "R158_LT_432")),
+            None
+        );
+        assert_eq!(
+            database
+                .by_descriptor(&VehicleTypeDescriptor::from_packed(1))
+                .map(|e| e.name.as_str()),
+            Some("Test")
+        );
+    }
+}
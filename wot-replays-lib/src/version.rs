@@ -0,0 +1,163 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// Version of the game client, e.g. `1.12.0.0 #774`. The versions are compared component
+/// by component, the revision is compared last. Some older hotfix versions have the fifth
+/// component, e.g. `0.9.17.0.3`, it's zero for the others.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ClientVersion {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+    pub build: u32,
+    /// The fifth component of the older hotfix versions
+    pub hotfix: u32,
+    /// Revision of the client (the `#774` part of the version from the XML)
+    pub revision: Option<u32>,
+}
+
+impl ClientVersion {
+    pub fn new(major: u32, minor: u32, patch: u32, build: u32) -> ClientVersion {
+        ClientVersion {
+            major,
+            minor,
+            patch,
+            build,
+            hotfix: 0,
+            revision: None,
+        }
+    }
+
+    /// Returns the version without the revision, useful for the range checks
+    pub fn without_revision(&self) -> ClientVersion {
+        ClientVersion {
+            revision: None,
+            ..*self
+        }
+    }
+}
+
+impl fmt::Display for ClientVersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}.{}.{}.{}",
+            self.major, self.minor, self.patch, self.build
+        )?;
+        if self.hotfix != 0 {
+            write!(f, ".{}", self.hotfix)?;
+        }
+        if let Some(revision) = self.revision {
+            write!(f, " #{}", revision)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for ClientVersion {
+    type Err = &'static str;
+
+    /// Parses the version from the `BattleInfo.client_version_from_exe` (`1.12.0.0`) and
+    /// `BattleInfo.client_version_from_xml` (`World of Tanks v.1.12.0.0 #774`) formats
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let start = s
+            .find(|c: char| c.is_ascii_digit())
+            .ok_or("No version number found")?;
+        let rest = &s[start..];
+        let end = rest
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .unwrap_or(rest.len());
+        let mut components = [0u32; 5];
+        let mut count = 0;
+        for component in rest[..end].trim_end_matches('.').split('.') {
+            if count == components.len() {
+                return Err("Too many version components");
+            }
+            components[count] = component.parse().map_err(|_| "Invalid version component")?;
+            count += 1;
+        }
+        if count < 2 {
+            return Err("At least major and minor versions expected");
+        }
+        let revision = match rest[end..].find('#') {
+            Some(i) => {
+                let digits: String = rest[end + i + 1..]
+                    .chars()
+                    .take_while(|c| c.is_ascii_digit())
+                    .collect();
+                Some(digits.parse().map_err(|_| "Invalid revision")?)
+            }
+            None => None,
+        };
+        Ok(ClientVersion {
+            major: components[0],
+            minor: components[1],
+            patch: components[2],
+            build: components[3],
+            hotfix: components[4],
+            revision,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_client_version_parsing() {
+        let version: ClientVersion = "World\u{a0}of\u{a0}Tanks v.1.12.0.0 #774".parse().unwrap();
+        assert_eq!(
+            version,
+            ClientVersion {
+                revision: Some(774),
+                ..ClientVersion::new(1, 12, 0, 0)
+            }
+        );
+        assert_eq!(version.to_string(), "1.12.0.0 #774");
+        assert_eq!(
+            "ver.1.12.1.0 #1234".parse::<ClientVersion>().unwrap(),
+            ClientVersion {
+                revision: Some(1234),
+                ..ClientVersion::new(1, 12, 1, 0)
+            }
+        );
+        assert_eq!(
+            "1.12.0.0".parse::<ClientVersion>().unwrap(),
+            ClientVersion::new(1, 12, 0, 0)
+        );
+        assert_eq!(
+            "0.9.20.1".parse::<ClientVersion>().unwrap(),
+            ClientVersion::new(0, 9, 20, 1)
+        );
+        let version = "World of Tanks v.0.9.17.0.3 #348"
+            .parse::<ClientVersion>()
+            .unwrap();
+        assert_eq!(
+            version,
+            ClientVersion {
+                hotfix: 3,
+                revision: Some(348),
+                ..ClientVersion::new(0, 9, 17, 0)
+            }
+        );
+        assert_eq!(version.to_string(), "0.9.17.0.3 #348");
+        assert!("1.2.3.4.5.6".parse::<ClientVersion>().is_err());
+        assert!("World of Tanks".parse::<ClientVersion>().is_err());
+        assert!("1".parse::<ClientVersion>().is_err());
+    }
+
+    #[test]
+    fn test_client_version_ordering() {
+        assert!(ClientVersion::new(0, 9, 20, 1) < ClientVersion::new(1, 0, 0, 0));
+        assert!(ClientVersion::new(1, 9, 0, 0) < ClientVersion::new(1, 12, 0, 0));
+        assert!(ClientVersion::new(1, 12, 0, 0) < ClientVersion::new(1, 12, 1, 0));
+        let hotfix = ClientVersion {
+            hotfix: 1,
+            ..ClientVersion::new(0, 9, 17, 0)
+        };
+        assert!(ClientVersion::new(0, 9, 17, 0) < hotfix);
+        assert!(hotfix < ClientVersion::new(0, 9, 17, 1));
+    }
+}